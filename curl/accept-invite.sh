@@ -0,0 +1,7 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+token=$1
+
+curl -s --request POST localhost:3030/api/v1/invites/"$token" -H "authorization: $authorization" | jq
@@ -4,4 +4,4 @@
 
 entry_id=$1
 
-curl -s --request DELETE localhost:3030/api/v1/entries/"$entry_id" -H "authorization: basic $basic_token" --include
+curl -s --request DELETE localhost:3030/api/v1/entries/"$entry_id" -H "authorization: $authorization" --include
@@ -0,0 +1,9 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+group_id=$1
+# defaults to the user of the credentials, which leaves the group
+identifier=${2:-"$username"}
+
+curl -s --request DELETE localhost:3030/api/v1/groups/"$group_id"/users/"$identifier" -H "authorization: $authorization" --include
@@ -0,0 +1,7 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+group_id=$1
+
+curl -s --request DELETE localhost:3030/api/v1/groups/"$group_id" -H "authorization: $authorization" --include
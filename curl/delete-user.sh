@@ -0,0 +1,5 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+curl -s --request DELETE localhost:3030/api/v1/users/"$username" -H "authorization: $authorization" --include
@@ -2,4 +2,7 @@
 
 . credentials || exit 1
 
-curl localhost:3030/api/v1/entries -H "authorization: basic $basic_token" -s | jq -C
+# optional query, e.g. "bought=false&sort=-created&limit=10"
+query="$1"
+
+curl "localhost:3030/api/v1/entries?$query" -H "authorization: $authorization" -s | jq -C
@@ -0,0 +1,7 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+group_id=${1:-"1"}
+
+curl -s localhost:3030/api/v1/groups/$group_id/lists -H "authorization: $authorization" | jq -C
@@ -7,4 +7,4 @@ product=${2:-"Apple"}
 
 payload=$(jo "product=$product")
 
-curl -s --request PATCH localhost:3030/api/v1/entries/"$entry_id" -H "authorization: basic $basic_token" -H "content-type: application/json" -d "$payload" | jq
+curl -s --request PATCH localhost:3030/api/v1/entries/"$entry_id" -H "authorization: $authorization" -H "content-type: application/json" -d "$payload" | jq
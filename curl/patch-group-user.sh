@@ -0,0 +1,11 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+group_id=${1:-"1"}
+identifier=${2:-"bob"}
+role=${3:-"admin"}
+
+payload=$(jo "role=$role")
+
+curl -s --request PATCH localhost:3030/api/v1/groups/"$group_id"/users/"$identifier" -H "authorization: $authorization" -H "content-type: application/json" -d "$payload" | jq
@@ -0,0 +1,10 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+group_id=${1:-"1"}
+name=${2:-"Morrison Family"}
+
+payload=$(jo "name=$name")
+
+curl -s --request PATCH localhost:3030/api/v1/groups/"$group_id" -H "authorization: $authorization" -H "content-type: application/json" -d "$payload" | jq
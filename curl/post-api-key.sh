@@ -0,0 +1,12 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+# api keys can't be used to create other api keys
+
+name=${1:-"kitchen tablet"}
+read_only=${2:-"false"}
+
+payload=$(jo "name=$name" "read_only=$read_only")
+
+curl -s localhost:3030/api/v1/users/"$username"/api-keys -H "authorization: basic $basic_token" -H "content-type: application/json" -d "$payload" | jq
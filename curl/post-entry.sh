@@ -10,4 +10,4 @@ group_id="2"
 
 payload=$(jo "product=$product" "amount=$amount" "unit=$unit" "note=$note" "group_id=$group_id")
 
-curl localhost:3030/api/v1/entries -H "authorization: basic $basic_token" -H "content-type: application/json" -d "$payload" | jq
+curl localhost:3030/api/v1/entries -H "authorization: $authorization" -H "content-type: application/json" -d "$payload" | jq
@@ -0,0 +1,10 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+group_id=${1:-"1"}
+single_use=${2:-"true"}
+
+payload=$(jo "single_use=$single_use")
+
+curl -s localhost:3030/api/v1/groups/"$group_id"/invites -H "authorization: $authorization" -H "content-type: application/json" -d "$payload" | jq
@@ -0,0 +1,10 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+group_id=${1:-"1"}
+identifier=${2:-"bob"}
+
+payload=$(jo "identifier=$identifier")
+
+curl -s localhost:3030/api/v1/groups/"$group_id"/users -H "authorization: $authorization" -H "content-type: application/json" -d "$payload" | jq
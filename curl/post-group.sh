@@ -0,0 +1,9 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+name=${1:-"Flatmates"}
+
+payload=$(jo "name=$name")
+
+curl -s localhost:3030/api/v1/groups -H "authorization: $authorization" -H "content-type: application/json" -d "$payload" | jq
@@ -0,0 +1,6 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+# the returned token can be used with "authorization: bearer <token>"
+curl -s --request POST localhost:3030/api/v1/sessions -H "authorization: basic $basic_token" | jq
@@ -0,0 +1,10 @@
+#!/usr/bin/env bash
+
+# registering does not require credentials
+username=${1:-"carol"}
+display_name=${2:-"Carol"}
+password=${3:-"carolcarol"}
+
+payload=$(jo "username=$username" "display_name=$display_name" "password=$password")
+
+curl -s localhost:3030/api/v1/users -H "content-type: application/json" -d "$payload" | jq
@@ -0,0 +1,15 @@
+#!/usr/bin/env bash
+
+. credentials || exit 1
+
+# optional group id, otherwise the changes of all visible entries are streamed
+group_id="$1"
+
+if [ -n "$group_id" ]; then
+    url="localhost:3030/api/v1/groups/$group_id/entries/events"
+else
+    url="localhost:3030/api/v1/entries/events"
+fi
+
+# -N disables buffering, so every event is printed as soon as it arrives
+curl "$url" -H "authorization: $authorization" -s -N
@@ -1,12 +1,14 @@
-insert into entries (product, amount, unit, note, user_id, group_id) values
+insert into entries (product, amount, unit, note, user_id, list_id) values
 -- Alice' added entries
-('Apples', 6, 'pieces', 'Any sort is ok.', 1, 1),
-('Bread', 1, 'piece', 'Wholegrain', 1, null),
-('Water', 3, 'l', null, 1, null),
+('Apples', 6, 'pieces', 'Any sort is ok.', 1, (select id from lists where group_id = 1 and is_default)),
+('Bread', 1, 'piece', 'Wholegrain', 1, (select id from lists where user_id = 1 and is_default)),
+('Water', 3, 'l', null, 1, (select id from lists where user_id = 1 and is_default)),
 -- Bob's added entries
-('Bananas', 6, 'pieces', 'I want to bake banana bread, so overripe ones are ok', 2, 1),
-('Chicken breast', 500, 'g', null, 2, null),
-('Water', 3, 'l', 'Carbonated', 2, null),
+('Bananas', 6, 'pieces', 'I want to bake banana bread, so overripe ones are ok', 2, (select id from lists where group_id = 1 and is_default)),
+('Chicken breast', 500, 'g', null, 2, (select id from lists where user_id = 2 and is_default)),
+('Water', 3, 'l', 'Carbonated', 2, (select id from lists where user_id = 2 and is_default)),
 -- Bob also added water to the MegaTech Corporation list
-('Water', 30, 'l', 'Carbonated', 2, 2),
-('Water', 30, 'l', 'Regular', 2, 2)
\ No newline at end of file
+('Water', 30, 'l', 'Carbonated', 2, (select id from lists where group_id = 2 and is_default)),
+('Water', 30, 'l', 'Regular', 2, (select id from lists where group_id = 2 and is_default)),
+-- Alice also needs something from the hardware store
+('Screws', 20, 'pieces', '4x40 mm', 1, (select id from lists where group_id = 1 and name = 'Hardware store'))
@@ -0,0 +1,4 @@
+-- every user and group already has a default list, that is created by a trigger
+insert into lists (name, group_id) values
+('Hardware store', 1),
+('Pharmacy', 1);
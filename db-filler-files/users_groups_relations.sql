@@ -1,4 +1,4 @@
-insert into users_groups_relations (user_id, group_id) values
-(1, 1), -- Alice is member of Morrison Family
-(2, 1), -- so is Bob
-(2, 2) -- additionally Bob is a member of MegaTech Corporation
\ No newline at end of file
+insert into users_groups_relations (user_id, group_id, role) values
+(1, 1, 'owner'), -- Alice is the owner of Morrison Family
+(2, 1, 'member'), -- Bob is a member of it
+(2, 2, 'owner') -- additionally Bob is the owner of MegaTech Corporation.
\ No newline at end of file
@@ -0,0 +1,14 @@
+create type group_role as enum ('owner', 'admin', 'member');
+
+alter table users_groups_relations
+    add column role group_role not null default 'member';
+
+-- the member of a group that joined first becomes its owner,
+-- so that every existing group can still be managed
+update users_groups_relations
+set role = 'owner'
+where (user_id, group_id) in (
+    select distinct on (group_id) user_id, group_id
+    from users_groups_relations
+    order by group_id, created, user_id
+);
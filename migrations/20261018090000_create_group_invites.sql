@@ -0,0 +1,13 @@
+create table group_invites
+(
+    id              bigserial       primary key,
+    group_id        bigint          not null,
+    token           varchar(64)     unique not null,
+    single_use      boolean         not null default false,
+    uses            integer         not null default 0,
+    expires         timestamptz     null,
+    created_by      bigint          not null,
+    created         timestamptz     not null default now(),
+    constraint group_invites_group_id_fk      foreign key (group_id) references groups (id) on delete cascade,
+    constraint group_invites_created_by_fk    foreign key (created_by) references users (id) on delete cascade
+);
@@ -0,0 +1,7 @@
+-- entries that deleted users created in groups can be reassigned to this user,
+-- so they stay on the lists of the groups instead of disappearing
+-- the id is outside of the range of the sequence, so it does not collide with registered users
+-- the password is the hash of a random string that was thrown away,
+-- so nobody can authenticate as this user
+insert into users (id, username, password, display_name) values
+(0, 'deleted-user', '$2a$12$1.zLB.YJSQQt6AR33pRtXuCHqGcbCX6ewIcaH/obatsETsxoFr3t6', 'Deleted user');
@@ -0,0 +1,11 @@
+create table sessions
+(
+    id              bigserial       primary key,
+    user_id         bigint          not null,
+    -- only the sha-256 hash of the token is stored, the token itself is only known to the client
+    token_hash      bytea           unique not null,
+    created         timestamptz     not null default now(),
+    expires         timestamptz     not null,
+    last_used       timestamptz     null,
+    constraint sessions_user_id_fk    foreign key (user_id) references users (id) on delete cascade
+);
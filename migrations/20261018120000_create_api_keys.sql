@@ -0,0 +1,16 @@
+create table api_keys
+(
+    id              bigserial       primary key,
+    user_id         bigint          not null,
+    name            varchar(80)     not null,
+    -- only the sha-256 hash of the key is stored, like the tokens of sessions
+    key_hash        bytea           unique not null,
+    read_only       boolean         not null default false,
+    -- if set, the key can only be used for this group
+    group_id        bigint          null,
+    expires         timestamptz     null,
+    created         timestamptz     not null default now(),
+    last_used       timestamptz     null,
+    constraint api_keys_user_id_fk     foreign key (user_id) references users (id) on delete cascade,
+    constraint api_keys_group_id_fk    foreign key (group_id) references groups (id) on delete cascade
+);
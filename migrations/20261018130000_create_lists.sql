@@ -0,0 +1,67 @@
+create table lists
+(
+    id              bigserial       primary key,
+    name            varchar(80)     not null,
+    -- a list either belongs to a group or is a personal list of a user
+    group_id        bigint          null,
+    user_id         bigint          null,
+    -- the list that is used when an entry is created without a list
+    is_default      boolean         not null default false,
+    created         timestamptz     not null default now(),
+    updated         timestamptz     null,
+    constraint lists_group_id_fk    foreign key (group_id) references groups (id) on delete cascade,
+    constraint lists_user_id_fk     foreign key (user_id) references users (id) on delete cascade,
+    constraint lists_owner_check    check ((group_id is null) <> (user_id is null))
+);
+
+create unique index lists_default_of_group_idx on lists (group_id) where is_default;
+create unique index lists_default_of_user_idx on lists (user_id) where is_default;
+
+create trigger set_updated_on_lists
+before update on lists
+for each row
+execute procedure trigger_set_updated();
+
+-- every group and every user gets a default list, so entries always have a list
+create function trigger_create_default_list()
+returns trigger as $$
+begin
+  if tg_table_name = 'groups' then
+    insert into lists (name, group_id, is_default) values ('Shopping list', new.id, true);
+  else
+    insert into lists (name, user_id, is_default) values ('Shopping list', new.id, true);
+  end if;
+  return new;
+end;
+$$ language plpgsql;
+
+create trigger create_default_list_of_groups
+after insert on groups
+for each row
+execute procedure trigger_create_default_list();
+
+create trigger create_default_list_of_users
+after insert on users
+for each row
+execute procedure trigger_create_default_list();
+
+insert into lists (name, group_id, is_default)
+select 'Shopping list', id, true from groups;
+
+insert into lists (name, user_id, is_default)
+select 'Shopping list', id, true from users;
+
+-- entries are moved from their group, or their creator if they have no group, to the default list
+alter table entries
+    add column list_id bigint null,
+    add constraint entries_list_id_fk foreign key (list_id) references lists (id) on delete cascade;
+
+update entries as e
+set list_id = l.id
+from lists as l
+where l.is_default
+    and (l.group_id = e.group_id or e.group_id is null and l.user_id = e.user_id);
+
+alter table entries
+    alter column list_id set not null,
+    drop column group_id;
@@ -0,0 +1,4 @@
+-- entries that were bought before this column existed keep an unknown buyer
+alter table entries
+    add column bought_by bigint null,
+    add constraint entries_bought_by_fk foreign key (bought_by) references users (id) on delete set null;
@@ -0,0 +1,30 @@
+create type entry_event_kind as enum ('created', 'updated', 'bought', 'unbought', 'deleted');
+
+create table entry_events
+(
+    id              bigserial           primary key,
+    -- not a foreign key, so the history of an entry is kept after it has been deleted
+    entry_id        bigint              not null,
+    list_id         bigint              not null,
+    actor_id        bigint              null,
+    kind            entry_event_kind    not null,
+    -- the changed fields of the entry, each with its old and new value
+    changes         jsonb               not null,
+    created         timestamptz         not null default now(),
+    constraint entry_events_list_id_fk     foreign key (list_id) references lists (id) on delete cascade,
+    constraint entry_events_actor_id_fk    foreign key (actor_id) references users (id) on delete set null
+);
+
+create index entry_events_entry_id_idx on entry_events (entry_id);
+create index entry_events_list_id_idx on entry_events (list_id);
+
+-- the history of existing entries starts with their creation
+insert into entry_events (entry_id, list_id, actor_id, kind, changes, created)
+select id, list_id, user_id, 'created', jsonb_build_object(
+    'product', jsonb_build_object('old', null, 'new', product),
+    'amount', jsonb_build_object('old', null, 'new', amount),
+    'unit', jsonb_build_object('old', null, 'new', unit),
+    'note', jsonb_build_object('old', null, 'new', note)
+), created
+from entries
+order by id;
@@ -0,0 +1,9 @@
+-- deleted entries are kept in the trash until they are restored or purged
+alter table entries
+    add column deleted timestamptz null,
+    add column deleted_by bigint null,
+    add constraint entries_deleted_by_fk foreign key (deleted_by) references users (id) on delete set null;
+
+create index entries_deleted_idx on entries (deleted) where deleted is not null;
+
+alter type entry_event_kind add value 'restored';
@@ -0,0 +1,22 @@
+-- bought entries are moved into an archive, so they disappear from the lists but stay queryable
+create table archives
+(
+    id              bigserial       primary key,
+    -- an archive either contains entries of a group or personal entries of a user
+    group_id        bigint          null,
+    user_id         bigint          null,
+    created_by      bigint          null,
+    created         timestamptz     not null default now(),
+    constraint archives_group_id_fk      foreign key (group_id) references groups (id) on delete cascade,
+    constraint archives_user_id_fk       foreign key (user_id) references users (id) on delete cascade,
+    constraint archives_created_by_fk    foreign key (created_by) references users (id) on delete set null,
+    constraint archives_owner_check      check ((group_id is null) <> (user_id is null))
+);
+
+alter table entries
+    add column archive_id bigint null,
+    add constraint entries_archive_id_fk foreign key (archive_id) references archives (id) on delete cascade;
+
+create index entries_archive_id_idx on entries (archive_id);
+
+alter type entry_event_kind add value 'archived';
@@ -0,0 +1,24 @@
+create table trips
+(
+    id              bigserial       primary key,
+    list_id         bigint          not null,
+    -- the user that goes shopping, entries they buy during the trip are added to it
+    shopper_id      bigint          null,
+    store           varchar(80)     null,
+    started         timestamptz     not null default now(),
+    ended           timestamptz     null,
+    constraint trips_list_id_fk       foreign key (list_id) references lists (id) on delete cascade,
+    constraint trips_shopper_id_fk    foreign key (shopper_id) references users (id) on delete set null
+);
+
+-- a user can only be on one trip at a time
+create unique index trips_active_shopper_idx on trips (shopper_id) where ended is null;
+
+alter table entries
+    add column trip_id bigint null,
+    -- the price that has been paid for the whole amount
+    add column price numeric(12, 2) null,
+    add constraint entries_trip_id_fk foreign key (trip_id) references trips (id) on delete set null,
+    add constraint entries_price_check check (price >= 0);
+
+create index entries_trip_id_idx on entries (trip_id);
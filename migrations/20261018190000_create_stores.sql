@@ -0,0 +1,54 @@
+create table stores
+(
+    id              bigserial       primary key,
+    name            varchar(80)     not null,
+    -- like lists, a store either belongs to a group or is a personal store of a user
+    group_id        bigint          null,
+    user_id         bigint          null,
+    created         timestamptz     not null default now(),
+    updated         timestamptz     null,
+    constraint stores_group_id_fk   foreign key (group_id) references groups (id) on delete cascade,
+    constraint stores_user_id_fk    foreign key (user_id) references users (id) on delete cascade,
+    constraint stores_owner_check   check ((group_id is null) <> (user_id is null))
+);
+
+create unique index stores_name_of_group_idx on stores (group_id, lower(name)) where group_id is not null;
+create unique index stores_name_of_user_idx on stores (user_id, lower(name)) where user_id is not null;
+
+create trigger set_updated_on_stores
+before update on stores
+for each row
+execute procedure trigger_set_updated();
+
+-- the store of a trip was free text until now, it becomes a store of the owner of the list
+insert into stores (name, group_id, user_id)
+select distinct on (coalesce(l.group_id, -l.user_id), lower(t.store)) t.store, l.group_id, l.user_id
+from trips as t
+join lists as l on l.id = t.list_id
+where t.store is not null;
+
+alter table trips add column store_id bigint null;
+alter table trips add constraint trips_store_id_fk foreign key (store_id) references stores (id) on delete set null;
+
+update trips as t set store_id = s.id
+from lists as l, stores as s
+where l.id = t.list_id
+    and s.group_id is not distinct from l.group_id
+    and s.user_id is not distinct from l.user_id
+    and lower(s.name) = lower(t.store);
+
+alter table trips drop column store;
+
+alter table entries
+    add column store_id bigint null,
+    -- an iso 4217 code like 'EUR', the price is in an unknown currency without it
+    add column currency char(3) null,
+    add constraint entries_store_id_fk foreign key (store_id) references stores (id) on delete set null,
+    add constraint entries_currency_check check (currency ~ '^[A-Z]{3}$');
+
+-- entries bought during a trip to a store have been bought there
+update entries as e set store_id = t.store_id
+from trips as t
+where t.id = e.trip_id and t.store_id is not null;
+
+create index entries_product_idx on entries (lower(product)) where price is not null;
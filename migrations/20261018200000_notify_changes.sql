@@ -0,0 +1,78 @@
+-- every instance of the server listens on this channel and forwards the changes to its clients,
+-- notifications are only delivered when the transaction commits
+create function trigger_notify_entry_change()
+returns trigger as $$
+declare
+  kind text;
+begin
+  if tg_op = 'INSERT' then
+    kind := 'created';
+  elsif old.deleted is null and new.deleted is not null then
+    kind := 'deleted';
+  elsif old.deleted is not null and new.deleted is null then
+    kind := 'restored';
+  elsif old.archive_id is null and new.archive_id is not null then
+    kind := 'archived';
+  elsif old.bought is null and new.bought is not null then
+    kind := 'bought';
+  elsif old.bought is not null and new.bought is null then
+    kind := 'unbought';
+  else
+    kind := 'updated';
+  end if;
+  perform pg_notify('shoppinglist_changes', json_build_object(
+    'table', tg_table_name,
+    'entry_id', new.id,
+    'list_id', new.list_id,
+    'group_id', (select group_id from lists where id = new.list_id),
+    'kind', kind
+  )::text);
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger notify_entry_change
+after insert or update on entries
+for each row
+execute procedure trigger_notify_entry_change();
+
+-- the access to the lists of a group can change with the group and its members
+create function trigger_notify_group_change()
+returns trigger as $$
+begin
+  perform pg_notify('shoppinglist_changes', json_build_object(
+    'table', tg_table_name,
+    'group_id', old.id
+  )::text);
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger notify_group_change
+after update or delete on groups
+for each row
+execute procedure trigger_notify_group_change();
+
+create function trigger_notify_membership_change()
+returns trigger as $$
+declare
+  relation users_groups_relations;
+begin
+  if tg_op = 'DELETE' then
+    relation := old;
+  else
+    relation := new;
+  end if;
+  perform pg_notify('shoppinglist_changes', json_build_object(
+    'table', tg_table_name,
+    'group_id', relation.group_id,
+    'user_id', relation.user_id
+  )::text);
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger notify_membership_change
+after insert or update or delete on users_groups_relations
+for each row
+execute procedure trigger_notify_membership_change();
@@ -0,0 +1,16 @@
+-- counts the changes of an entry, so clients can make sure they don't overwrite changes
+-- they haven't seen yet
+alter table entries add column version bigint not null default 1;
+
+create function trigger_increment_version()
+returns trigger as $$
+begin
+  new.version = old.version + 1;
+  return new;
+end;
+$$ language plpgsql;
+
+create trigger increment_version_on_entries
+before update on entries
+for each row
+execute procedure trigger_increment_version();
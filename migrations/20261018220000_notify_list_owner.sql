@@ -0,0 +1,35 @@
+-- the owner of a personal list is part of the notification,
+-- so the event streams can skip the changes of other users without querying the entry
+create or replace function trigger_notify_entry_change()
+returns trigger as $$
+declare
+  kind text;
+  list lists;
+begin
+  select * into list from lists where id = new.list_id;
+  if tg_op = 'INSERT' then
+    kind := 'created';
+  elsif old.deleted is null and new.deleted is not null then
+    kind := 'deleted';
+  elsif old.deleted is not null and new.deleted is null then
+    kind := 'restored';
+  elsif old.archive_id is null and new.archive_id is not null then
+    kind := 'archived';
+  elsif old.bought is null and new.bought is not null then
+    kind := 'bought';
+  elsif old.bought is not null and new.bought is null then
+    kind := 'unbought';
+  else
+    kind := 'updated';
+  end if;
+  perform pg_notify('shoppinglist_changes', json_build_object(
+    'table', tg_table_name,
+    'entry_id', new.id,
+    'list_id', new.list_id,
+    'group_id', list.group_id,
+    'user_id', list.user_id,
+    'kind', kind
+  )::text);
+  return null;
+end;
+$$ language plpgsql;
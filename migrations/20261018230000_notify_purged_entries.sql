@@ -0,0 +1,78 @@
+-- entries that are deleted permanently are notified as purged, when the trash is emptied
+-- or with the user that created them
+create or replace function trigger_notify_entry_change()
+returns trigger as $$
+declare
+  entry entries;
+  kind text;
+  list lists;
+begin
+  if tg_op = 'DELETE' then
+    entry := old;
+  else
+    entry := new;
+  end if;
+  select * into list from lists where id = entry.list_id;
+  -- the list has been deleted with its entries, their purge has been notified by the list
+  if not found then
+    return null;
+  end if;
+  if tg_op = 'INSERT' then
+    kind := 'created';
+  elsif tg_op = 'DELETE' then
+    kind := 'purged';
+  elsif old.deleted is null and new.deleted is not null then
+    kind := 'deleted';
+  elsif old.deleted is not null and new.deleted is null then
+    kind := 'restored';
+  elsif old.archive_id is null and new.archive_id is not null then
+    kind := 'archived';
+  elsif old.bought is null and new.bought is not null then
+    kind := 'bought';
+  elsif old.bought is not null and new.bought is null then
+    kind := 'unbought';
+  else
+    kind := 'updated';
+  end if;
+  perform pg_notify('shoppinglist_changes', json_build_object(
+    'table', tg_table_name,
+    'entry_id', entry.id,
+    'list_id', entry.list_id,
+    'group_id', list.group_id,
+    'user_id', list.user_id,
+    'kind', kind
+  )::text);
+  return null;
+end;
+$$ language plpgsql;
+
+drop trigger notify_entry_change on entries;
+
+create trigger notify_entry_change
+after insert or update or delete on entries
+for each row
+execute procedure trigger_notify_entry_change();
+
+-- the entries of a list are deleted with it, but once they are, their list can't be found anymore,
+-- so their purge is notified before the list is deleted
+create function trigger_notify_purge_of_list_entries()
+returns trigger as $$
+begin
+  perform pg_notify('shoppinglist_changes', json_build_object(
+    'table', 'entries',
+    'entry_id', id,
+    'list_id', list_id,
+    'group_id', old.group_id,
+    'user_id', old.user_id,
+    'kind', 'purged'
+  )::text)
+  from entries
+  where list_id = old.id;
+  return old;
+end;
+$$ language plpgsql;
+
+create trigger notify_purge_of_list_entries
+before delete on lists
+for each row
+execute procedure trigger_notify_purge_of_list_entries();
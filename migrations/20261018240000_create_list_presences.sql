@@ -0,0 +1,41 @@
+create type presence_status as enum ('viewing', 'shopping');
+
+-- the connections to the channels of the lists, shared by every instance of the server,
+-- a user can be connected multiple times
+create table list_presences
+(
+    id          bigserial           primary key,
+    list_id     bigint              not null,
+    user_id     bigint              not null,
+    status      presence_status     not null default 'viewing',
+    -- refreshed with every heartbeat, so the connections of instances that stopped can be removed
+    seen        timestamptz         not null default now(),
+    constraint list_presences_list_id_fk    foreign key (list_id) references lists (id) on delete cascade,
+    constraint list_presences_user_id_fk    foreign key (user_id) references users (id) on delete cascade
+);
+
+create index list_presences_list_id_idx on list_presences (list_id);
+
+create function trigger_notify_presence_change()
+returns trigger as $$
+declare
+  presence list_presences;
+begin
+  if tg_op = 'DELETE' then
+    presence := old;
+  else
+    presence := new;
+  end if;
+  perform pg_notify('shoppinglist_changes', json_build_object(
+    'table', tg_table_name,
+    'list_id', presence.list_id
+  )::text);
+  return null;
+end;
+$$ language plpgsql;
+
+-- refreshing a connection doesn't change who is connected
+create trigger notify_presence_change
+after insert or delete or update of status on list_presences
+for each row
+execute procedure trigger_notify_presence_change();
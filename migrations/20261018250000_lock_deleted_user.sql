@@ -0,0 +1,2 @@
+-- the password of the deleted user is not a bcrypt hash, so no password can ever match it
+update users set password = '!' where id = 0;
@@ -0,0 +1,11 @@
+-- like sessions and api keys, only the hash of the token of an invite is stored,
+-- so the invites can't be accepted by anyone who can read the database
+alter table group_invites
+    add column token_hash bytea null;
+
+update group_invites set token_hash = sha256(convert_to(token, 'UTF8'));
+
+alter table group_invites
+    alter column token_hash set not null,
+    add constraint group_invites_token_hash_key unique (token_hash),
+    drop column token;
@@ -0,0 +1,358 @@
+use actix_web::http::Method;
+use sqlx::{Pool, Postgres};
+
+use super::token;
+
+// distinguishes api keys from session tokens, both are sent as bearer tokens
+pub const KEY_PREFIX: &str = "slk_";
+
+pub fn generate() -> String {
+    format!("{KEY_PREFIX}{}", token::generate())
+}
+
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(KEY_PREFIX)
+}
+
+// inserted into the request extensions by the middleware,
+// if the request was authenticated with an api key
+#[derive(Clone, Copy, Debug)]
+pub struct ApiKeyScope {
+    // the id of the api key, so long running connections can check that it still exists
+    pub id: i64,
+    pub read_only: bool,
+    pub group_id: Option<i64>,
+}
+
+// the resources below the top level collections of the api that belong to a group,
+// e.g. "/lists/{id}"
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GroupResource {
+    Entry,
+    Archive,
+    List,
+    Store,
+    Trip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GroupPathAccess {
+    Allowed,
+    Denied,
+    // only if the resource with the id belongs to the group, which needs a query
+    IfOwned(GroupResource, i64),
+}
+
+fn is_read_only_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+// keys that are limited to a group can only read the group itself, use its entries, lists,
+// archives, stores and activity and the lists, archives, trips, stores and entries of the group
+// the members and invites of the group can't be managed with them, so a leaked key
+// can't be used to take over or delete the group
+fn group_path_access(group_id: i64, method: &Method, path: &str) -> GroupPathAccess {
+    let mut segments = path.trim_start_matches('/').split('/');
+    let collection = segments.next().unwrap_or_default();
+    let id_option = segments.next().and_then(|id| id.parse::<i64>().ok());
+    let resource = match (collection, id_option) {
+        // the index of the api
+        ("", None) => return GroupPathAccess::Allowed,
+        ("groups", Some(id)) if id == group_id => {
+            return match segments.next() {
+                None | Some("") if is_read_only_method(method) => GroupPathAccess::Allowed,
+                Some("entries" | "lists" | "archives" | "stores" | "activity") => {
+                    GroupPathAccess::Allowed
+                }
+                _ => GroupPathAccess::Denied,
+            };
+        }
+        ("entries", Some(_)) => GroupResource::Entry,
+        ("archives", Some(_)) => GroupResource::Archive,
+        ("lists", Some(_)) => GroupResource::List,
+        ("stores", Some(_)) => GroupResource::Store,
+        ("trips", Some(_)) => GroupResource::Trip,
+        _ => return GroupPathAccess::Denied,
+    };
+    match id_option {
+        Some(id) => GroupPathAccess::IfOwned(resource, id),
+        None => GroupPathAccess::Denied,
+    }
+}
+
+async fn is_owned_by_group(
+    pool: &Pool<Postgres>,
+    resource: GroupResource,
+    id: i64,
+    group_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let exists = match resource {
+        GroupResource::Entry => {
+            sqlx::query!(
+                r#"select exists (
+                    select 1 from entries inner join lists on lists.id = entries.list_id
+                    where entries.id = $1 and lists.group_id = $2
+                ) as "exists!: bool""#,
+                id,
+                group_id,
+            )
+            .fetch_one(pool)
+            .await?
+            .exists
+        }
+        GroupResource::Archive => {
+            sqlx::query!(
+                r#"select exists (select 1 from archives where id = $1 and group_id = $2) as "exists!: bool""#,
+                id,
+                group_id,
+            )
+            .fetch_one(pool)
+            .await?
+            .exists
+        }
+        GroupResource::List => {
+            sqlx::query!(
+                r#"select exists (select 1 from lists where id = $1 and group_id = $2) as "exists!: bool""#,
+                id,
+                group_id,
+            )
+            .fetch_one(pool)
+            .await?
+            .exists
+        }
+        GroupResource::Store => {
+            sqlx::query!(
+                r#"select exists (select 1 from stores where id = $1 and group_id = $2) as "exists!: bool""#,
+                id,
+                group_id,
+            )
+            .fetch_one(pool)
+            .await?
+            .exists
+        }
+        GroupResource::Trip => {
+            sqlx::query!(
+                r#"select exists (
+                    select 1 from trips join lists on lists.id = trips.list_id
+                    where trips.id = $1 and lists.group_id = $2
+                ) as "exists!: bool""#,
+                id,
+                group_id,
+            )
+            .fetch_one(pool)
+            .await?
+            .exists
+        }
+    };
+    Ok(exists)
+}
+
+// returns why the request is not allowed with the api key, if it is not
+// the path has to be relative to the api, e.g. "/groups/1/users"
+// only the method and the path of the request are checked, so every way to write that doesn't use
+// the method of the request, like the commands sent through a websocket opened with GET,
+// has to check the ApiKeyScope itself
+pub async fn scope_violation(
+    pool: &Pool<Postgres>,
+    scope: &ApiKeyScope,
+    method: &Method,
+    path: &str,
+) -> Result<Option<&'static str>, sqlx::Error> {
+    if scope.read_only && !is_read_only_method(method) {
+        return Ok(Some("the api key can only be used for reading"));
+    }
+    let Some(group_id) = scope.group_id else {
+        return Ok(None);
+    };
+
+    let allowed = match group_path_access(group_id, method, path) {
+        GroupPathAccess::Allowed => true,
+        GroupPathAccess::Denied => false,
+        GroupPathAccess::IfOwned(resource, id) => {
+            is_owned_by_group(pool, resource, id, group_id).await?
+        }
+    };
+    if !allowed {
+        return Ok(Some("the api key can only be used for its group"));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_keys_can_only_use_reading_methods() {
+        assert!(is_read_only_method(&Method::GET));
+        assert!(is_read_only_method(&Method::HEAD));
+        assert!(is_read_only_method(&Method::OPTIONS));
+        assert!(!is_read_only_method(&Method::POST));
+        assert!(!is_read_only_method(&Method::PATCH));
+        assert!(!is_read_only_method(&Method::PUT));
+        assert!(!is_read_only_method(&Method::DELETE));
+    }
+
+    #[test]
+    fn websockets_are_opened_with_a_reading_method() {
+        // the channel of a list has to reject the writing commands of read only keys itself
+        assert!(is_read_only_method(&Method::GET));
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/lists/4/channel"),
+            GroupPathAccess::IfOwned(GroupResource::List, 4)
+        );
+    }
+
+    #[test]
+    fn group_keys_can_access_their_group() {
+        assert_eq!(
+            group_path_access(1, &Method::GET, ""),
+            GroupPathAccess::Allowed
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/"),
+            GroupPathAccess::Allowed
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/groups/1"),
+            GroupPathAccess::Allowed
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/groups/1/entries/events"),
+            GroupPathAccess::Allowed
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/groups/2"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/groups/2/users"),
+            GroupPathAccess::Denied
+        );
+    }
+
+    #[test]
+    fn group_keys_cant_manage_their_group() {
+        assert_eq!(
+            group_path_access(1, &Method::PATCH, "/groups/1"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::DELETE, "/groups/1"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/groups/1/users"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::PATCH, "/groups/1/users/alice"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::DELETE, "/groups/1/users/2"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::POST, "/groups/1/invites"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::DELETE, "/groups/1/invites/3"),
+            GroupPathAccess::Denied
+        );
+    }
+
+    #[test]
+    fn group_keys_can_use_the_entries_and_lists_of_their_group() {
+        for path in [
+            "/groups/1/entries",
+            "/groups/1/entries/events",
+            "/groups/1/lists",
+            "/groups/1/archives",
+            "/groups/1/stores",
+            "/groups/1/activity",
+        ] {
+            assert_eq!(
+                group_path_access(1, &Method::GET, path),
+                GroupPathAccess::Allowed
+            );
+        }
+        assert_eq!(
+            group_path_access(1, &Method::POST, "/groups/1/entries"),
+            GroupPathAccess::Allowed
+        );
+        assert_eq!(
+            group_path_access(1, &Method::POST, "/groups/1/archives"),
+            GroupPathAccess::Allowed
+        );
+    }
+
+    #[test]
+    fn group_keys_cant_access_collections_of_all_groups() {
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/entries"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/entries/batch"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/entries/trash"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/entries/events"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/groups"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/products/milk/prices"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/users/1"),
+            GroupPathAccess::Denied
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/sessions"),
+            GroupPathAccess::Denied
+        );
+    }
+
+    #[test]
+    fn group_keys_can_access_resources_owned_by_their_group() {
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/entries/9"),
+            GroupPathAccess::IfOwned(GroupResource::Entry, 9)
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/lists/4"),
+            GroupPathAccess::IfOwned(GroupResource::List, 4)
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/lists/4/entries"),
+            GroupPathAccess::IfOwned(GroupResource::List, 4)
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/archives/2/entries"),
+            GroupPathAccess::IfOwned(GroupResource::Archive, 2)
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/stores/3"),
+            GroupPathAccess::IfOwned(GroupResource::Store, 3)
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/trips/5/end"),
+            GroupPathAccess::IfOwned(GroupResource::Trip, 5)
+        );
+        assert_eq!(
+            group_path_access(1, &Method::GET, "/lists/abc"),
+            GroupPathAccess::Denied
+        );
+    }
+}
@@ -1,13 +1,18 @@
 use std::{
     future::{ready, Ready},
+    net::IpAddr,
     rc::Rc,
+    time::Duration,
 };
 
 use actix_web::{
     body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    http::StatusCode,
-    web::Data,
+    http::{
+        header::{self, HeaderValue},
+        StatusCode,
+    },
+    web::{self, Data},
     Error, HttpMessage, HttpResponse, HttpResponseBuilder,
 };
 use base64::{engine::general_purpose::STANDARD, Engine};
@@ -15,6 +20,15 @@ use futures_util::future::LocalBoxFuture;
 
 use crate::AppData;
 
+use super::{
+    api_key::{self, ApiKeyScope},
+    token, SessionId,
+};
+
+// authenticates requests with either basic credentials or a session token
+// and inserts the id of the user into the request extensions
+// requests without an authorization header are anonymous, they are only rejected if ABORT_IF_NO_USER is true
+// requests with invalid credentials are always rejected with 401
 pub struct Auth<const ABORT_IF_NO_USER: bool> {
     pub app_data: Data<AppData>,
 }
@@ -52,7 +66,7 @@ fn starts_with_ignore_case(haystack: &str, needle: &str) -> bool {
     // but there is no way of knowing, which iterator
     // returned None
     // for (hc, nc) in h_chars.zip(n_chars) {
-    //     if hc.to_ascii_lowercase() != nc.to_ascii_lowercase() {
+    //     if !hc.eq_ignore_ascii_case(&nc) {
     //         return false;
     //     }
     // }
@@ -71,7 +85,7 @@ fn starts_with_ignore_case(haystack: &str, needle: &str) -> bool {
             None => return false,
             Some(hc) => hc,
         };
-        if hc.to_ascii_lowercase() != nc.to_ascii_lowercase() {
+        if !hc.eq_ignore_ascii_case(&nc) {
             return false;
         }
     }
@@ -97,48 +111,167 @@ fn extract_identifier_and_password(auth_header: &str) -> Option<(String, String)
         .map(|(id_str, password_str)| (id_str.to_string(), password_str.to_string()))
 }
 
-macro_rules! ok_or_log_and_respond_service_internal_server_error {
-    ($result: expr, $req: expr) => {
-        match $result {
-            Ok(res) => res,
-            Err(err) => {
-                log::error!("Internal server error: {}", err);
-                let (request, _) = $req.into_parts();
-                let error_response =
-                    HttpResponse::InternalServerError().json("internal server error");
-                let response = error_response.map_into_right_body();
-                return Ok(ServiceResponse::new(request, response));
-            }
+fn extract_bearer_token(auth_header: &str) -> Option<String> {
+    let trimmed = auth_header.trim_start();
+    if !starts_with_ignore_case(trimmed, "bearer") {
+        return None;
+    }
+    // since we checked that the first 6 bytes are "bearer"
+    // we can safely slice
+    let token = trimmed[6..].trim();
+    if token.is_empty() {
+        return None;
+    }
+    Some(token.to_string())
+}
+
+// used to verify a password, if no user with the supplied username exists,
+// so that the response time does not reveal which usernames exist
+// precomputed with the default cost of bcrypt, which the passwords of the users are hashed with,
+// so it doesn't have to be hashed on the worker
+const DUMMY_PASSWORD_HASH: &str = "$2a$12$3clzXcWqmv5uVrHNLs1tOe9OG9SoLK5.y9dhYjqwcbmcnj6N6NHbW";
+
+enum AuthenticationError {
+    // the credentials are malformed, unknown or wrong
+    // the client is not told which one, so usernames can't be enumerated
+    InvalidCredentials,
+    // too many failed attempts for the username or the ip address
+    TooManyAttempts(Duration),
+    Internal(Box<dyn std::error::Error>),
+}
+
+impl<E: std::error::Error + 'static> From<E> for AuthenticationError {
+    fn from(err: E) -> Self {
+        AuthenticationError::Internal(Box::new(err))
+    }
+}
+
+struct Authenticated {
+    user_id: i64,
+    session_id: Option<SessionId>,
+    api_key_scope: Option<ApiKeyScope>,
+}
+
+async fn authenticate_with_password(
+    app_data: &AppData,
+    ip: Option<IpAddr>,
+    username: String,
+    password: String,
+) -> Result<Authenticated, AuthenticationError> {
+    // checked before the password is verified,
+    // so a locked out client can't keep the server busy with bcrypt
+    let attempt = app_data
+        .login_throttle
+        .check(Some(&username), ip)
+        .map_err(AuthenticationError::TooManyAttempts)?;
+
+    let user_option = sqlx::query!(
+        "select id, password from users where username = $1",
+        &username
+    )
+    .fetch_optional(&app_data.pool)
+    .await?;
+    let (user_id, password_hash) = match user_option {
+        // users that can't log in, like the deleted user, have a password that is not a hash
+        Some(user) if user.password.parse::<bcrypt::HashParts>().is_ok() => {
+            (Some(user.id), user.password)
         }
+        _ => (None, DUMMY_PASSWORD_HASH.to_string()),
     };
-}
 
-macro_rules! some_or_respond_service_bad_request {
-    ($result: expr, $req: expr) => {
-        match $result {
-            Some(res) => res,
-            None => {
-                let (request, _) = $req.into_parts();
-                let error_response = HttpResponse::BadRequest().json("bad request");
-                let response = error_response.map_into_right_body();
-                return Ok(ServiceResponse::new(request, response));
-            }
+    // bcrypt is slow on purpose, so it must not block the worker
+    let verified = web::block(move || bcrypt::verify(password, &password_hash)).await??;
+    match user_id {
+        Some(user_id) if verified => {
+            attempt.succeed();
+            Ok(Authenticated {
+                user_id,
+                session_id: None,
+                api_key_scope: None,
+            })
         }
+        _ => {
+            attempt.fail();
+            Err(AuthenticationError::InvalidCredentials)
+        }
+    }
+}
+
+// session tokens and api keys are not throttled, they can't be guessed
+// and looking up their hash is cheap, unlike verifying a password
+async fn authenticate_with_token(
+    app_data: &AppData,
+    token: String,
+) -> Result<Authenticated, AuthenticationError> {
+    if api_key::is_api_key(&token) {
+        // the last use is tracked, so it can be shown in the list of api keys
+        let api_key_option = sqlx::query!(
+            "update api_keys set last_used = now() where key_hash = $1 and (expires is null or expires > now()) returning id, user_id, read_only, group_id",
+            token::hash(&token)
+        )
+        .fetch_optional(&app_data.pool)
+        .await?;
+        let Some(api_key) = api_key_option else {
+            return Err(AuthenticationError::InvalidCredentials);
+        };
+
+        return Ok(Authenticated {
+            user_id: api_key.user_id,
+            session_id: None,
+            api_key_scope: Some(ApiKeyScope {
+                id: api_key.id,
+                read_only: api_key.read_only,
+                group_id: api_key.group_id,
+            }),
+        });
+    }
+
+    // the last use is tracked, so it can be shown in the list of sessions
+    let session_option = sqlx::query!(
+        "update sessions set last_used = now() where token_hash = $1 and expires > now() returning id, user_id",
+        token::hash(&token)
+    )
+    .fetch_optional(&app_data.pool)
+    .await?;
+    let Some(session) = session_option else {
+        return Err(AuthenticationError::InvalidCredentials);
     };
+
+    Ok(Authenticated {
+        user_id: session.user_id,
+        session_id: Some(SessionId(session.id)),
+        api_key_scope: None,
+    })
 }
 
-macro_rules! some_or_respond_service_not_found {
-    ($result: expr, $req: expr) => {
-        match $result {
-            Some(res) => res,
-            None => {
-                let (request, _) = $req.into_parts();
-                let error_response = HttpResponse::NotFound().json("resource not found");
-                let response = error_response.map_into_right_body();
-                return Ok(ServiceResponse::new(request, response));
-            }
-        }
+// returns None if the request does not contain any credentials, meaning the client is anonymous
+async fn authenticate(
+    app_data: &AppData,
+    ip: Option<IpAddr>,
+    auth_header_option: Option<&HeaderValue>,
+) -> Result<Option<Authenticated>, AuthenticationError> {
+    let Some(auth_header) = auth_header_option else {
+        return Ok(None);
     };
+    let auth_header_str = auth_header
+        .to_str()
+        .map_err(|_| AuthenticationError::InvalidCredentials)?;
+
+    if let Some(token) = extract_bearer_token(auth_header_str) {
+        return authenticate_with_token(app_data, token).await.map(Some);
+    }
+    let (username, password) = extract_identifier_and_password(auth_header_str)
+        .ok_or(AuthenticationError::InvalidCredentials)?;
+    authenticate_with_password(app_data, ip, username, password)
+        .await
+        .map(Some)
+}
+
+fn unauthorized_response(message: &str) -> HttpResponse {
+    HttpResponseBuilder::new(StatusCode::UNAUTHORIZED)
+        .append_header((header::WWW_AUTHENTICATE, "Basic"))
+        .append_header((header::WWW_AUTHENTICATE, "Bearer"))
+        .json(message)
 }
 
 impl<S, B, const ABORT_IF_NO_USER: bool> Service<ServiceRequest>
@@ -159,46 +292,97 @@ where
         let app_data = self.app_data.clone();
 
         Box::pin(async move {
-            let headers = req.headers();
-
-            let auth_header_option = headers.get("authorization");
+            // the request might already have been authenticated by an outer instance of this middleware
+            if !req.extensions().contains::<i64>() {
+                // the address of the peer is used instead of the "x-forwarded-for" header,
+                // since the header can be set to anything by the client
+                let ip = req.peer_addr().map(|address| address.ip());
+                let auth_header_option = req.headers().get(header::AUTHORIZATION);
+                let authentication_result = authenticate(&app_data, ip, auth_header_option).await;
 
-            if auth_header_option.is_none() && ABORT_IF_NO_USER {
-                let (request, _) = req.into_parts();
-                let mut response_builder = HttpResponseBuilder::new(StatusCode::UNAUTHORIZED);
-                response_builder.append_header(("www-authenticate", "Basic"));
-                let error_response = response_builder.json("supply an authorization header");
-                let response = error_response.map_into_right_body();
-                return Ok(ServiceResponse::new(request, response));
+                let error_response = match authentication_result {
+                    Ok(Some(authenticated)) => {
+                        req.extensions_mut().insert(authenticated.user_id);
+                        if let Some(session_id) = authenticated.session_id {
+                            req.extensions_mut().insert(session_id);
+                        }
+                        if let Some(api_key_scope) = authenticated.api_key_scope {
+                            req.extensions_mut().insert(api_key_scope);
+                        }
+                        None
+                    }
+                    Ok(None) if ABORT_IF_NO_USER => {
+                        Some(unauthorized_response("supply an authorization header"))
+                    }
+                    // anonymous requests are allowed to continue, the handlers decide what they can access
+                    Ok(None) => None,
+                    // failed authentication is rejected, even if authentication is optional,
+                    // otherwise the client would be treated as anonymous without noticing
+                    Err(AuthenticationError::InvalidCredentials) => {
+                        Some(unauthorized_response("invalid credentials"))
+                    }
+                    Err(AuthenticationError::TooManyAttempts(retry_after)) => Some(
+                        HttpResponseBuilder::new(StatusCode::TOO_MANY_REQUESTS)
+                            .insert_header((
+                                header::RETRY_AFTER,
+                                // rounded up, so the client does not retry too early
+                                (retry_after.as_secs() + 1).to_string(),
+                            ))
+                            .json("too many failed authentication attempts, try again later"),
+                    ),
+                    Err(AuthenticationError::Internal(err)) => {
+                        log::error!("Internal server error: {}", err);
+                        Some(HttpResponse::InternalServerError().json("internal server error"))
+                    }
+                };
+                if let Some(error_response) = error_response {
+                    let (request, _) = req.into_parts();
+                    let response = error_response.map_into_right_body();
+                    return Ok(ServiceResponse::new(request, response));
+                }
             }
 
-            if let Some(auth_header) = auth_header_option {
-                let auth_header_str =
-                    some_or_respond_service_bad_request!(auth_header.to_str().ok(), req);
-                let id_and_password_option = extract_identifier_and_password(auth_header_str);
-                let (id, password) =
-                    some_or_respond_service_bad_request!(id_and_password_option, req);
-
-                let user_result =
-                    sqlx::query!("select id, password from users where username = $1", &id)
-                        .fetch_optional(&app_data.pool)
-                        .await;
-                let user_option =
-                    ok_or_log_and_respond_service_internal_server_error!(user_result, req);
-                let user = some_or_respond_service_not_found!(user_option, req);
-
-                let verification_result = bcrypt::verify(password, &user.password);
-                let verified =
-                    ok_or_log_and_respond_service_internal_server_error!(verification_result, req);
-                if verified {
-                    req.extensions_mut().insert(user.id);
+            // the scope of api keys is checked by the instance that wraps the api,
+            // because only there the unprocessed part of the path is relative to the api
+            let api_key_scope_option = req.extensions().get::<ApiKeyScope>().copied();
+            if let (true, Some(api_key_scope)) = (ABORT_IF_NO_USER, api_key_scope_option) {
+                let violation_result = api_key::scope_violation(
+                    &app_data.pool,
+                    &api_key_scope,
+                    req.method(),
+                    req.match_info().unprocessed(),
+                )
+                .await;
+                let error_response = match violation_result {
+                    Ok(None) => None,
+                    Ok(Some(violation)) => Some(HttpResponse::Forbidden().json(violation)),
+                    Err(err) => {
+                        log::error!("Internal server error: {}", err);
+                        Some(HttpResponse::InternalServerError().json("internal server error"))
+                    }
+                };
+                if let Some(error_response) = error_response {
+                    let (request, _) = req.into_parts();
+                    let response = error_response.map_into_right_body();
+                    return Ok(ServiceResponse::new(request, response));
                 }
             }
-            let fut = service.call(req);
 
             // regular endpoint
-            let res = fut.await?.map_into_left_body();
+            let res = service.call(req).await?.map_into_left_body();
             Ok(res)
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dummy_password_hash_costs_as_much_as_the_passwords_of_users() {
+        let hash = DUMMY_PASSWORD_HASH.parse::<bcrypt::HashParts>().unwrap();
+        assert_eq!(hash.get_cost(), bcrypt::DEFAULT_COST);
+        assert!(bcrypt::verify("dummy password", DUMMY_PASSWORD_HASH).unwrap());
+    }
+}
@@ -1 +1,9 @@
+pub mod api_key;
 pub mod middleware;
+pub mod throttle;
+pub mod token;
+
+// inserted into the request extensions by the middleware,
+// if the request was authenticated with a session token
+#[derive(Clone, Copy, Debug)]
+pub struct SessionId(pub i64);
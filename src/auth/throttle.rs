@@ -0,0 +1,391 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+// failed attempts that are allowed before the first lockout
+const FREE_ATTEMPTS_PER_USERNAME: u32 = 5;
+// multiple users can share an ip address, e.g. a household behind one router
+const FREE_ATTEMPTS_PER_IP: u32 = 20;
+// the lockout doubles with every further failed attempt, up to the maximum
+const BASE_LOCKOUT: Duration = Duration::from_secs(1);
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+// failed attempts are forgotten, if there was no further failure for this long
+const FORGET_AFTER: Duration = Duration::from_secs(60 * 60);
+// expired records are only removed once there are this many,
+// so the map does not have to be iterated on every failure
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum ThrottleKey {
+    // the username is only locked out for the ip address the failures came from,
+    // otherwise anyone could keep a user from logging in by failing on purpose
+    Username(String, Option<IpAddr>),
+    Ip(IpAddr),
+}
+
+struct Attempts {
+    // failed attempts, until they are forgotten
+    failed: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+    // attempts that passed the check, but whose outcome is not known yet
+    in_progress: u32,
+}
+
+impl Attempts {
+    fn is_unused(&self) -> bool {
+        self.failed == 0 && self.in_progress == 0
+    }
+}
+
+// tracks failed authentication attempts per username and ip address and per ip address
+// and locks them out with exponential backoff
+// the state is only kept in memory, so every instance of the server throttles on its own
+#[derive(Default)]
+pub struct LoginThrottle {
+    attempts: Mutex<HashMap<ThrottleKey, Attempts>>,
+}
+
+fn keys(username: Option<&str>, ip: Option<IpAddr>) -> Vec<(ThrottleKey, u32)> {
+    let mut keys = Vec::with_capacity(2);
+    if let Some(username) = username {
+        keys.push((
+            ThrottleKey::Username(username.to_string(), ip),
+            FREE_ATTEMPTS_PER_USERNAME,
+        ));
+    }
+    if let Some(ip) = ip {
+        keys.push((ThrottleKey::Ip(ip), FREE_ATTEMPTS_PER_IP));
+    }
+    keys
+}
+
+fn lockout_duration(count: u32, free_attempts: u32) -> Option<Duration> {
+    let exponent = count.checked_sub(free_attempts)?;
+    // 2^10 seconds already exceed the maximum
+    let factor = 2u32.pow(exponent.min(10));
+    Some((BASE_LOCKOUT * factor).min(MAX_LOCKOUT))
+}
+
+// once an attempt failed, the remaining free attempts can't be used up by parallel attempts,
+// which all pass the check before the first of them fails, so they are shared by the attempts
+// in progress and the failed ones, after each lockout only one attempt at a time is allowed
+// without failures parallel attempts are not limited, so clients with valid credentials
+// can make as many requests as they like
+fn allows_another_attempt(attempts: &Attempts, free_attempts: u32) -> bool {
+    attempts.failed == 0
+        || attempts.in_progress < free_attempts.saturating_sub(attempts.failed).max(1)
+}
+
+impl LoginThrottle {
+    // returns how long the client has to wait, if the username or the ip address is locked out
+    // or has too many attempts in progress
+    // the returned attempt has to be finished with its outcome, if there is one
+    pub fn check(
+        &self,
+        username: Option<&str>,
+        ip: Option<IpAddr>,
+    ) -> Result<Attempt<'_>, Duration> {
+        self.check_at(username, ip, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        username: Option<&str>,
+        ip: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<Attempt<'_>, Duration> {
+        let keys = keys(username, ip);
+        let mut attempts = self.attempts.lock().unwrap_or_else(|err| err.into_inner());
+        let mut retry_after = None;
+        for (key, free_attempts) in &keys {
+            let Some(attempts) = attempts.get_mut(key) else {
+                continue;
+            };
+            if now - attempts.last_failure >= FORGET_AFTER {
+                attempts.failed = 0;
+                attempts.locked_until = None;
+            }
+            let wait = match attempts.locked_until {
+                Some(locked_until) if locked_until > now => Some(locked_until - now),
+                // the attempts in progress finish within the time it takes to verify a password
+                _ if !allows_another_attempt(attempts, *free_attempts) => Some(BASE_LOCKOUT),
+                _ => None,
+            };
+            retry_after = retry_after.max(wait);
+        }
+        if let Some(retry_after) = retry_after {
+            return Err(retry_after);
+        }
+
+        if attempts.len() >= PRUNE_THRESHOLD {
+            attempts.retain(|_, attempts| {
+                attempts.in_progress > 0 || now - attempts.last_failure < FORGET_AFTER
+            });
+        }
+        for (key, _) in &keys {
+            attempts
+                .entry(key.clone())
+                .or_insert(Attempts {
+                    failed: 0,
+                    last_failure: now,
+                    locked_until: None,
+                    in_progress: 0,
+                })
+                .in_progress += 1;
+        }
+        Ok(Attempt {
+            throttle: self,
+            keys,
+        })
+    }
+}
+
+// an attempt that passed the check of the throttle,
+// it counts as in progress until it is dropped
+pub struct Attempt<'a> {
+    throttle: &'a LoginThrottle,
+    keys: Vec<(ThrottleKey, u32)>,
+}
+
+impl Attempt<'_> {
+    pub fn fail(self) {
+        self.fail_at(Instant::now());
+    }
+
+    fn fail_at(self, now: Instant) {
+        let mut attempts = self
+            .throttle
+            .attempts
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        for (key, free_attempts) in &self.keys {
+            let Some(attempts) = attempts.get_mut(key) else {
+                continue;
+            };
+            if now - attempts.last_failure >= FORGET_AFTER {
+                attempts.failed = 0;
+            }
+            attempts.failed += 1;
+            attempts.last_failure = now;
+
+            if let Some(lockout) = lockout_duration(attempts.failed, *free_attempts) {
+                attempts.locked_until = Some(now + lockout);
+                log::warn!(
+                    target: "audit",
+                    "Locked out {:?} for {}s after {} failed authentication attempts",
+                    key,
+                    lockout.as_secs(),
+                    attempts.failed,
+                );
+            }
+        }
+    }
+
+    // only the username is reset, otherwise an attacker could reset the counter of their ip address
+    // by regularly authenticating with an account they own
+    pub fn succeed(self) {
+        let mut attempts = self
+            .throttle
+            .attempts
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        for (key, _) in &self.keys {
+            if let (ThrottleKey::Username(..), Some(attempts)) = (key, attempts.get_mut(key)) {
+                attempts.failed = 0;
+                attempts.locked_until = None;
+            }
+        }
+    }
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        let mut attempts = self
+            .throttle
+            .attempts
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        for (key, _) in &self.keys {
+            let Some(key_attempts) = attempts.get_mut(key) else {
+                continue;
+            };
+            key_attempts.in_progress = key_attempts.in_progress.saturating_sub(1);
+            if key_attempts.is_unused() {
+                attempts.remove(key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn fail(throttle: &LoginThrottle, username: Option<&str>, ip: Option<IpAddr>, now: Instant) {
+        throttle.check_at(username, ip, now).unwrap().fail_at(now);
+    }
+
+    #[test]
+    fn lockout_duration_starts_after_free_attempts() {
+        assert_eq!(lockout_duration(0, 5), None);
+        assert_eq!(lockout_duration(4, 5), None);
+        assert_eq!(lockout_duration(5, 5), Some(BASE_LOCKOUT));
+        assert_eq!(lockout_duration(6, 5), Some(BASE_LOCKOUT * 2));
+        assert_eq!(lockout_duration(8, 5), Some(BASE_LOCKOUT * 8));
+    }
+
+    #[test]
+    fn lockout_duration_is_capped() {
+        assert_eq!(lockout_duration(15, 5), Some(MAX_LOCKOUT));
+        assert_eq!(lockout_duration(u32::MAX, 5), Some(MAX_LOCKOUT));
+    }
+
+    #[test]
+    fn locks_out_after_free_attempts() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        for _ in 0..FREE_ATTEMPTS_PER_USERNAME {
+            fail(&throttle, Some("alice"), None, now);
+        }
+        assert_eq!(
+            throttle.check_at(Some("alice"), None, now).err(),
+            Some(BASE_LOCKOUT)
+        );
+        // other usernames are not affected
+        assert!(throttle.check_at(Some("bob"), None, now).is_ok());
+        assert!(throttle
+            .check_at(Some("alice"), None, now + BASE_LOCKOUT)
+            .is_ok());
+    }
+
+    #[test]
+    fn locks_out_the_username_only_for_the_ip_address_of_the_failures() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        let other_ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        for _ in 0..FREE_ATTEMPTS_PER_USERNAME {
+            fail(&throttle, Some("alice"), Some(IP), now);
+        }
+        assert!(throttle.check_at(Some("alice"), Some(IP), now).is_err());
+        assert!(throttle
+            .check_at(Some("alice"), Some(other_ip), now)
+            .is_ok());
+    }
+
+    #[test]
+    fn parallel_attempts_without_failures_are_not_limited() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        let in_progress = (0..FREE_ATTEMPTS_PER_IP * 2)
+            .map(|_| throttle.check_at(Some("alice"), Some(IP), now).unwrap())
+            .collect::<Vec<_>>();
+        assert!(throttle.check_at(Some("alice"), Some(IP), now).is_ok());
+        drop(in_progress);
+    }
+
+    #[test]
+    fn parallel_attempts_count_against_free_attempts_after_a_failure() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        fail(&throttle, Some("alice"), None, now);
+        let in_progress = (1..FREE_ATTEMPTS_PER_USERNAME)
+            .map(|_| throttle.check_at(Some("alice"), None, now).unwrap())
+            .collect::<Vec<_>>();
+        assert!(throttle.check_at(Some("alice"), None, now).is_err());
+        drop(in_progress);
+        assert!(throttle.check_at(Some("alice"), None, now).is_ok());
+    }
+
+    #[test]
+    fn only_one_attempt_at_a_time_after_lockout() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        for _ in 0..FREE_ATTEMPTS_PER_USERNAME {
+            fail(&throttle, Some("alice"), None, now);
+        }
+        let later = now + BASE_LOCKOUT;
+        let attempt = throttle.check_at(Some("alice"), None, later).unwrap();
+        assert!(throttle.check_at(Some("alice"), None, later).is_err());
+        attempt.fail_at(later);
+        assert_eq!(
+            throttle.check_at(Some("alice"), None, later).err(),
+            Some(BASE_LOCKOUT * 2)
+        );
+    }
+
+    #[test]
+    fn forgets_failures_after_a_while() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        for _ in 0..FREE_ATTEMPTS_PER_USERNAME {
+            fail(&throttle, Some("alice"), None, now);
+        }
+        let later = now + FORGET_AFTER;
+        fail(&throttle, Some("alice"), None, later);
+        // the failure after the pause is the first one again
+        let attempts = throttle.attempts.lock().unwrap();
+        let attempts = &attempts[&ThrottleKey::Username("alice".to_string(), None)];
+        assert_eq!(attempts.failed, 1);
+        assert_eq!(attempts.locked_until, None);
+    }
+
+    #[test]
+    fn success_only_resets_the_username() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        for _ in 0..FREE_ATTEMPTS_PER_USERNAME - 1 {
+            fail(&throttle, Some("alice"), Some(IP), now);
+        }
+        throttle
+            .check_at(Some("alice"), Some(IP), now)
+            .unwrap()
+            .succeed();
+        let attempts = throttle.attempts.lock().unwrap();
+        assert!(!attempts.contains_key(&ThrottleKey::Username("alice".to_string(), Some(IP))));
+        assert_eq!(
+            attempts[&ThrottleKey::Ip(IP)].failed,
+            FREE_ATTEMPTS_PER_USERNAME - 1
+        );
+    }
+
+    #[test]
+    fn finished_attempts_without_failures_are_not_kept() {
+        let throttle = LoginThrottle::default();
+        drop(throttle.check_at(Some("alice"), Some(IP), Instant::now()));
+        assert!(throttle.attempts.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn prunes_forgotten_records() {
+        let throttle = LoginThrottle::default();
+        let now = Instant::now();
+        for address in 0..PRUNE_THRESHOLD as u32 {
+            fail(
+                &throttle,
+                None,
+                Some(IpAddr::V4(Ipv4Addr::from(address))),
+                now,
+            );
+        }
+        let recent = IpAddr::V4(Ipv4Addr::from(u32::MAX));
+        fail(&throttle, None, Some(recent), now + FORGET_AFTER / 2);
+        assert_eq!(throttle.attempts.lock().unwrap().len(), PRUNE_THRESHOLD + 1);
+
+        let attempt = throttle
+            .check_at(Some("alice"), None, now + FORGET_AFTER)
+            .unwrap();
+        let attempts = throttle.attempts.lock().unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts.contains_key(&ThrottleKey::Ip(recent)));
+        drop(attempts);
+        drop(attempt);
+    }
+}
@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 use actix_web::{
     middleware::{self, Logger},
     web::{self},
@@ -6,6 +8,35 @@ use actix_web::{
 
 struct AppData {
     pool: sqlx::PgPool,
+    deleted_user_entries_policy: DeletedUserEntriesPolicy,
+    // how long a session token can be used after logging in
+    session_lifetime: chrono::Duration,
+    login_throttle: auth::throttle::LoginThrottle,
+    // pushes the changes of entries to the clients that are listening for them
+    live_updates: v1::LiveUpdates,
+}
+
+// decides what happens to the entries a user created in groups, when the user deletes their account
+// personal entries are always deleted
+#[derive(Clone, Copy, Debug)]
+enum DeletedUserEntriesPolicy {
+    // the entries are kept and assigned to the "deleted-user"
+    Reassign,
+    Delete,
+}
+
+impl FromStr for DeletedUserEntriesPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reassign" => Ok(Self::Reassign),
+            "delete" => Ok(Self::Delete),
+            _ => Err(format!(
+                "invalid policy \"{s}\", expected \"reassign\" or \"delete\""
+            )),
+        }
+    }
 }
 
 // these two macros would also be used if there would be a "v2" of the api
@@ -30,6 +61,7 @@ macro_rules! generate_options_route {
 }
 
 mod auth;
+mod trash;
 mod v1;
 
 #[actix_web::main]
@@ -42,19 +74,57 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await
         .expect("Failed to connect to database");
 
-    let app_data = web::Data::new(AppData { pool: pg_pool });
+    let deleted_user_entries_policy = dotenvy::var("DELETED_USER_ENTRIES_POLICY")
+        .map(|value| {
+            DeletedUserEntriesPolicy::from_str(&value)
+                .expect("DELETED_USER_ENTRIES_POLICY must be valid")
+        })
+        .unwrap_or(DeletedUserEntriesPolicy::Reassign);
+
+    let session_lifetime_hours = dotenvy::var("SESSION_LIFETIME_HOURS")
+        .map(|value| {
+            value
+                .parse::<i64>()
+                .expect("SESSION_LIFETIME_HOURS must be a number")
+        })
+        .unwrap_or(30 * 24);
+
+    let trash_retention_days = dotenvy::var("TRASH_RETENTION_DAYS")
+        .map(|value| {
+            value
+                .parse::<i64>()
+                .expect("TRASH_RETENTION_DAYS must be a number")
+        })
+        .unwrap_or(30);
+
+    let app_data = web::Data::new(AppData {
+        pool: pg_pool,
+        deleted_user_entries_policy,
+        session_lifetime: chrono::Duration::hours(session_lifetime_hours),
+        login_throttle: auth::throttle::LoginThrottle::default(),
+        live_updates: v1::LiveUpdates::default(),
+    });
 
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info,sqlx=off,debug"));
 
+    actix_web::rt::spawn(trash::purge_periodically(
+        app_data.pool.clone(),
+        chrono::Duration::days(trash_retention_days),
+    ));
+    actix_web::rt::spawn(v1::forward_database_changes(app_data.clone()));
+
     let api_prefix = "/api/v1";
     const BIND_ADDRESS: &str = "0.0.0.0:3030";
     let server = HttpServer::new(move || {
-        let api_v1_scope =
-            Scope::new(api_prefix)
-                .configure(v1::configure_routes)
-                .wrap(auth::middleware::Auth::<true> {
+        // the public routes are registered first, so they are matched
+        // before the nested scope, which matches every other path and requires authentication
+        let api_v1_scope = Scope::new(api_prefix)
+            .configure(v1::configure_public_routes)
+            .service(Scope::new("").configure(v1::configure_routes).wrap(
+                auth::middleware::Auth::<true> {
                     app_data: app_data.clone(),
-                });
+                },
+            ));
 
         App::new()
             .app_data(app_data.clone())
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+// how often the trash is checked for entries that have been deleted long enough
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// permanently deletes the entries that have been in the trash for longer than the retention
+// runs until the server stops
+pub async fn purge_periodically(pool: sqlx::PgPool, retention: chrono::Duration) {
+    let mut interval = actix_web::rt::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let result = sqlx::query!(
+            "delete from entries where deleted < $1",
+            chrono::Utc::now() - retention,
+        )
+        .execute(&pool)
+        .await;
+        match result {
+            Ok(result) if result.rows_affected() > 0 => {
+                log::info!("Purged {} entries from the trash", result.rows_affected());
+            }
+            Ok(_) => {}
+            Err(err) => log::error!("Failed to purge the trash: {}", err),
+        }
+    }
+}
@@ -1,17 +1,43 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::{Duration, Instant, SystemTime},
+};
 
 use actix_web::{
-    http::StatusCode,
+    http::{
+        header::{self, ContentDisposition, DispositionParam, DispositionType},
+        StatusCode,
+    },
     web::{self, Json, ReqData},
-    HttpResponse, HttpResponseBuilder,
+    HttpMessage, HttpResponse, HttpResponseBuilder,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SubsecRound, Utc};
+use futures_util::stream;
 use is_empty::IsEmpty;
-use serde::{Deserialize, Deserializer};
-use sqlx::{Pool, Postgres, QueryBuilder};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{Acquire, PgConnection, PgExecutor, Pool, Postgres, QueryBuilder};
+use tokio::sync::broadcast::{self, error::RecvError};
 
-use crate::{v1::models::Entry, AppData};
+use crate::{
+    auth::{
+        api_key::{self, ApiKeyScope},
+        token, SessionId,
+    },
+    v1::models::Entry,
+    AppData, DeletedUserEntriesPolicy,
+};
 
-use super::models::{Group, User};
+use super::live::{EntryChange, LiveUpdate};
+use super::models::{
+    ApiKey, Archive, EntryEvent, EntryEventKind, ExportedMembership, ExportedUser, Group,
+    GroupMember, GroupRole, Invite, List, NewApiKey, NewInvite, NewSession, Presence,
+    PresenceStatus, ProductPrice, ProductPriceAverage, ProductPrices, RestResource, Session, Store,
+    Trip, TripTotal, User, UserExport,
+};
 
 macro_rules! url_for_static_or_return {
     ($request: expr, $name: expr) => {
@@ -38,6 +64,10 @@ pub async fn get_api_index(request: actix_web::HttpRequest) -> impl actix_web::R
             "groups",
             url_for_static_or_return!(&request, resource_name!("/groups")).to_string(),
         ),
+        (
+            "trash",
+            url_for_static_or_return!(&request, resource_name!("/entries/trash")).to_string(),
+        ),
         // was used for testing
         // (
         //     "users",
@@ -72,6 +102,59 @@ macro_rules! all_ok_or_log_and_respond_internal_server_error {
     }};
 }
 
+// responds with the body as json, unless the client already has the same representation,
+// then it responds with 304 and no body, which also works for head requests
+// without an etag, a strong one is derived from the exact bytes of the body
+// last_modified only has a resolution of seconds, so if-none-match takes precedence over
+// if-modified-since
+fn respond_with_validators(
+    request: &actix_web::HttpRequest,
+    mut response_builder: HttpResponseBuilder,
+    body: &impl Serialize,
+    etag: Option<header::EntityTag>,
+    last_modified: Option<DateTime<Utc>>,
+) -> HttpResponse {
+    let body = ok_or_log_and_respond_internal_server_error!(serde_json::to_vec(body));
+    let etag = etag.unwrap_or_else(|| {
+        header::EntityTag::new_strong(URL_SAFE_NO_PAD.encode(Sha256::digest(&body)))
+    });
+    let last_modified = last_modified.map(|last_modified| {
+        header::HttpDate::from(SystemTime::from(last_modified.trunc_subsecs(0)))
+    });
+
+    let is_not_modified = match request.get_header::<header::IfNoneMatch>() {
+        Some(header::IfNoneMatch::Any) => true,
+        Some(header::IfNoneMatch::Items(etags)) => etags.iter().any(|item| item.weak_eq(&etag)),
+        None => match (
+            request.get_header::<header::IfModifiedSince>(),
+            last_modified,
+        ) {
+            (Some(header::IfModifiedSince(since)), Some(last_modified)) => last_modified <= since,
+            _ => false,
+        },
+    };
+    if is_not_modified {
+        response_builder = HttpResponse::NotModified();
+    }
+    // the responses depend on the user, so they may only be cached by the client,
+    // which has to revalidate them before using them
+    response_builder.insert_header(header::CacheControl(vec![
+        header::CacheDirective::Private,
+        header::CacheDirective::NoCache,
+    ]));
+    response_builder.insert_header(header::ETag(etag));
+    if let Some(last_modified) = last_modified {
+        response_builder.insert_header(header::LastModified(last_modified));
+    }
+    if is_not_modified {
+        return response_builder.finish();
+    }
+
+    response_builder
+        .content_type(header::ContentType::json())
+        .body(body)
+}
+
 // was used for testing
 // pub async fn get_users(
 //     request: actix_web::HttpRequest,
@@ -109,10 +192,9 @@ pub async fn get_user_by_id_or_username(
             return HttpResponse::NotFound().json("user not found");
         }
     }
-    let user_option = ok_or_log_and_respond_internal_server_error!(
-        sqlx::query_as!(
-            User,
-            r#"select id, username, display_name from users where (username = $1 or id = $2) and id = $3"#,
+    let row_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"select id, username, display_name, coalesce(updated, created) as "last_modified!" from users where (username = $1 or id = $2) and id = $3"#,
             identifier.as_str(),
             identifier_parsed_option,
             user_id, // if the username was passed as identifier, this check makes sure that the user can't see other users
@@ -120,9 +202,14 @@ pub async fn get_user_by_id_or_username(
         .fetch_optional(&app_data.pool)
         .await
     );
-    let Some(user) = user_option else {
+    let Some(row) = row_option else {
         return HttpResponse::NotFound().json("user not found");
     };
+    let user = User {
+        id: row.id,
+        username: row.username,
+        display_name: row.display_name,
+    };
 
     // why does rest_resource log?
     // because it has access to the resource_name
@@ -132,394 +219,5698 @@ pub async fn get_user_by_id_or_username(
         return HttpResponse::InternalServerError().json("internal server error");
     };
 
-    HttpResponse::Ok().json(rest_resource)
+    respond_with_validators(
+        &request,
+        HttpResponse::Ok(),
+        &rest_resource,
+        None,
+        Some(row.last_modified),
+    )
 }
 
-pub async fn get_groups(
+// same limits as the columns of the "users" table
+const USERNAME_MAX_LENGTH: usize = 30;
+const DISPLAY_NAME_MAX_LENGTH: usize = 80;
+// bcrypt only uses the first 72 bytes of a password
+const PASSWORD_MIN_LENGTH: usize = 8;
+const PASSWORD_MAX_LENGTH: usize = 72;
+
+// mirrors the check constraint of the "username" column: ^[A-Za-z_][A-Za-z0-9_-]*$
+// since a username can't start with a digit, it can never be mistaken for an id
+fn is_valid_username(username: &str) -> bool {
+    let mut chars = username.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    username.len() <= USERNAME_MAX_LENGTH
+        && (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn is_valid_display_name(display_name: &str) -> bool {
+    !display_name.trim().is_empty() && display_name.chars().count() <= DISPLAY_NAME_MAX_LENGTH
+}
+
+fn is_valid_password(password: &str) -> bool {
+    (PASSWORD_MIN_LENGTH..=PASSWORD_MAX_LENGTH).contains(&password.len())
+}
+
+// hashing is deliberately slow, so it is done on the blocking thread pool
+// instead of stalling the worker
+async fn hash_password(password: String) -> Result<String, Box<dyn std::error::Error>> {
+    let hash = web::block(move || bcrypt::hash(password, bcrypt::DEFAULT_COST)).await??;
+    Ok(hash)
+}
+
+async fn verify_password(
+    password: String,
+    hash: String,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let verified = web::block(move || bcrypt::verify(password, &hash)).await??;
+    Ok(verified)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostUserRequestData {
+    username: String,
+    display_name: String,
+    password: String,
+}
+
+// registers a new user, this is the only endpoint that can be used without authentication
+pub(super) async fn post_user(
     request: actix_web::HttpRequest,
     app_data: web::Data<AppData>,
-    user_id: ReqData<i64>,
+    payload: Json<PostUserRequestData>,
 ) -> HttpResponse {
-    let user_id = user_id.into_inner();
-    let groups = ok_or_log_and_respond_internal_server_error!(
-        sqlx::query_as!(Group, r#"select id, name from groups inner join users_groups_relations as ugr on ugr.group_id = id and ugr.user_id = $1 order by id"#, user_id)
-            .fetch_all(&app_data.pool)
-            .await
-    );
-    let rest_resources = all_ok_or_log_and_respond_internal_server_error!(groups
-        .iter()
-        .map(|item| item.rest_resource(&request))
-        .collect::<Vec<_>>());
+    let payload = payload.into_inner();
+    if !is_valid_username(&payload.username) {
+        return HttpResponse::BadRequest().json(format!(
+            "username must start with a letter or an underscore, may only contain letters, digits, underscores and hyphens and must not be longer than {USERNAME_MAX_LENGTH} characters"
+        ));
+    }
+    if !is_valid_display_name(&payload.display_name) {
+        return HttpResponse::BadRequest().json(format!(
+            "display_name must not be empty and must not be longer than {DISPLAY_NAME_MAX_LENGTH} characters"
+        ));
+    }
+    if !is_valid_password(&payload.password) {
+        return HttpResponse::BadRequest().json(format!(
+            "password must be between {PASSWORD_MIN_LENGTH} and {PASSWORD_MAX_LENGTH} bytes long"
+        ));
+    }
 
-    HttpResponse::Ok().json(rest_resources)
+    let password_hash =
+        ok_or_log_and_respond_internal_server_error!(hash_password(payload.password).await);
+    let user_result = sqlx::query_as!(
+        User,
+        r#"insert into users (username, password, display_name) values ($1, $2, $3)
+        returning id, username, display_name"#,
+        payload.username,
+        password_hash,
+        payload.display_name,
+    )
+    .fetch_one(&app_data.pool)
+    .await;
+    let user = match user_result {
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            return HttpResponse::Conflict().json("username is already taken");
+        }
+        result => ok_or_log_and_respond_internal_server_error!(result),
+    };
+
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(user.rest_resource(&request));
+
+    HttpResponse::Created().json(rest_resource)
 }
 
-pub async fn get_group_by_id(
+#[derive(Deserialize)]
+pub(super) struct PatchUserRequestData {
+    display_name: String,
+}
+
+pub(super) async fn patch_user(
     request: actix_web::HttpRequest,
-    id: web::Path<i64>,
+    identifier: web::Path<String>,
     app_data: web::Data<AppData>,
     user_id: ReqData<i64>,
+    payload: Json<PatchUserRequestData>,
 ) -> HttpResponse {
+    if !is_valid_display_name(&payload.display_name) {
+        return HttpResponse::BadRequest().json(format!(
+            "display_name must not be empty and must not be longer than {DISPLAY_NAME_MAX_LENGTH} characters"
+        ));
+    }
     let user_id = user_id.into_inner();
-    let resource_option = ok_or_log_and_respond_internal_server_error!(
+    let user_option = ok_or_log_and_respond_internal_server_error!(
         sqlx::query_as!(
-            Group,
-            r#"select id, name from groups inner join users_groups_relations as ugr on ugr.group_id = id and ugr.user_id = $1 where id = $2"#,
-            user_id,
-            id.into_inner(),
+            User,
+            r#"update users set display_name = $1 where (username = $2 or id = $3) and id = $4
+            returning id, username, display_name"#,
+            payload.display_name,
+            identifier.as_str(),
+            identifier.parse::<i64>().ok(),
+            user_id, // users can only modify themselves
         )
         .fetch_optional(&app_data.pool)
         .await
     );
-
-    let Some(resource) = resource_option else {
-        return HttpResponse::NotFound().json("group not found");
+    let Some(user) = user_option else {
+        return HttpResponse::NotFound().json("user not found");
     };
 
-    let rest_resource =
-        ok_or_log_and_respond_internal_server_error!(resource.rest_resource(&request));
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(user.rest_resource(&request));
 
     HttpResponse::Ok().json(rest_resource)
 }
 
-pub async fn get_group_users(
-    request: actix_web::HttpRequest,
-    id: web::Path<i64>,
+#[derive(Deserialize)]
+pub(super) struct PutUserPasswordRequestData {
+    old_password: String,
+    new_password: String,
+}
+
+pub(super) async fn put_user_password(
+    identifier: web::Path<String>,
     app_data: web::Data<AppData>,
     user_id: ReqData<i64>,
+    session_id: Option<ReqData<SessionId>>,
+    payload: Json<PutUserPasswordRequestData>,
 ) -> HttpResponse {
-    let group_id = id.into_inner();
+    let payload = payload.into_inner();
+    if !is_valid_password(&payload.new_password) {
+        return HttpResponse::BadRequest().json(format!(
+            "new_password must be between {PASSWORD_MIN_LENGTH} and {PASSWORD_MAX_LENGTH} bytes long"
+        ));
+    }
     let user_id = user_id.into_inner();
-    let is_member = ok_or_log_and_respond_internal_server_error!(
-        is_member(&app_data.pool, user_id, group_id).await
+    let pool = &app_data.pool;
+    let user_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"select id, password from users where (username = $1 or id = $2) and id = $3"#,
+            identifier.as_str(),
+            identifier.parse::<i64>().ok(),
+            user_id, // users can only change their own password
+        )
+        .fetch_optional(pool)
+        .await
     );
-    if !is_member {
-        return HttpResponse::NotFound().json("group not found");
+    let Some(user) = user_option else {
+        return HttpResponse::NotFound().json("user not found");
+    };
+
+    // the old password is required even though the user is already authenticated,
+    // so that a stolen session can't be used to take over the account
+    let verified = ok_or_log_and_respond_internal_server_error!(
+        verify_password(payload.old_password, user.password).await
+    );
+    if !verified {
+        return HttpResponse::Forbidden().json("old_password is incorrect");
     }
-    let users = ok_or_log_and_respond_internal_server_error!(sqlx::query_as!(
-        User,
-        r#"select id, username, display_name from users inner join users_groups_relations as ugr on users.id = ugr.user_id where ugr.group_id = $1"#,
-        group_id,
-    ).fetch_all(&app_data.pool).await);
-    let body = all_ok_or_log_and_respond_internal_server_error!(users
-        .iter()
-        .map(|user| user.rest_resource(&request))
-        .collect::<Vec<_>>());
 
-    HttpResponse::Ok().json(body)
+    let password_hash =
+        ok_or_log_and_respond_internal_server_error!(hash_password(payload.new_password).await);
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            "update users set password = $1 where id = $2",
+            password_hash,
+            user.id,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    // the password might have been changed because it was leaked,
+    // so all other sessions are revoked as well
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            "delete from sessions where user_id = $1 and id is distinct from $2",
+            user.id,
+            session_id.map(|session_id| session_id.0),
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    HttpResponse::NoContent().finish()
 }
 
-pub async fn get_user_groups_by_id_or_username(
-    request: actix_web::HttpRequest,
+// the user that entries are reassigned to, it is inserted by a migration
+const DELETED_USER_ID: i64 = 0;
+
+pub(super) async fn delete_user(
     identifier: web::Path<String>,
     app_data: web::Data<AppData>,
     user_id: ReqData<i64>,
 ) -> HttpResponse {
-    let identifer_string = identifier.into_inner();
     let user_id = user_id.into_inner();
-    let groups = ok_or_log_and_respond_internal_server_error!(sqlx::query_as!(
-        Group,
-        r#"select id, name from groups inner join users_groups_relations as ugr on groups.id = ugr.group_id and ugr.user_id = $1 where ugr.user_id = $2 or ugr.user_id in (select id from users where username = $3)"#,
-        user_id,
-        identifer_string.parse::<i64>().ok(),
-        identifer_string
-    ).fetch_all(&app_data.pool).await);
+    let pool = &app_data.pool;
 
-    let body = all_ok_or_log_and_respond_internal_server_error!(groups
-        .iter()
-        .map(|resource| resource.rest_resource(&request))
-        .collect::<Vec<_>>());
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let user_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"select id from users where (username = $1 or id = $2) and id = $3 for update"#,
+            identifier.as_str(),
+            identifier.parse::<i64>().ok(),
+            user_id, // users can only delete themselves
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+    );
+    if user_option.is_none() {
+        return HttpResponse::NotFound().json("user not found");
+    }
 
-    HttpResponse::Ok().json(body)
+    if let DeletedUserEntriesPolicy::Reassign = app_data.deleted_user_entries_policy {
+        ok_or_log_and_respond_internal_server_error!(
+            sqlx::query!(
+                r#"update entries set user_id = $1
+                where user_id = $2 and list_id in (select id from lists where group_id is not null)"#,
+                DELETED_USER_ID,
+                user_id,
+            )
+            .execute(&mut *transaction)
+            .await
+        );
+        // otherwise the buyer of the entries would be set to null by the foreign key
+        ok_or_log_and_respond_internal_server_error!(
+            sqlx::query!(
+                r#"update entries set bought_by = $1 where bought_by = $2"#,
+                DELETED_USER_ID,
+                user_id,
+            )
+            .execute(&mut *transaction)
+            .await
+        );
+        ok_or_log_and_respond_internal_server_error!(
+            sqlx::query!(
+                r#"update entry_events set actor_id = $1 where actor_id = $2"#,
+                DELETED_USER_ID,
+                user_id,
+            )
+            .execute(&mut *transaction)
+            .await
+        );
+    }
+
+    // groups the user is the last owner of are handed over to the member with the highest role,
+    // that has been in the group the longest, so they can still be managed
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"update users_groups_relations set role = 'owner'
+            where (user_id, group_id) in (
+                select distinct on (ugr.group_id) ugr.user_id, ugr.group_id
+                from users_groups_relations as ugr
+                where ugr.user_id <> $1
+                    and ugr.group_id in (
+                        select group_id from users_groups_relations where user_id = $1 and role = 'owner'
+                    )
+                    and not exists (
+                        select 1 from users_groups_relations as owners
+                        where owners.group_id = ugr.group_id and owners.user_id <> $1 and owners.role = 'owner'
+                    )
+                order by ugr.group_id, ugr.role, ugr.created, ugr.user_id
+            )"#,
+            user_id,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+
+    // nobody could access groups without members anymore
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"delete from groups
+            where id in (select group_id from users_groups_relations where user_id = $1)
+                and not exists (
+                    select 1 from users_groups_relations where group_id = groups.id and user_id <> $1
+                )"#,
+            user_id,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+
+    // memberships, invites and the remaining entries are removed
+    // by the "on delete cascade" foreign keys
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!("delete from users where id = $1", user_id)
+            .execute(&mut *transaction)
+            .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    HttpResponse::NoContent().finish()
 }
 
-pub async fn get_entries(
-    request: actix_web::HttpRequest,
+pub(super) async fn get_user_export(
+    identifier: web::Path<String>,
     app_data: web::Data<AppData>,
     user_id: ReqData<i64>,
 ) -> HttpResponse {
+    let user_id = user_id.into_inner();
     let pool = &app_data.pool;
-    let rows_result = sqlx::query_as!(
-        Entry,
-        // only show entries of groups the user is a member of
-        // or their personal entries
-        // this does not show entries that were created by the user
-        // but the user is not part of the assigned group anymore
-        // this is intentional!
-        r#"select
-            e.id, e.product, e.amount, e.unit, e.note, e.created, e.bought, e.user_id, e.group_id
-            from
-                entries as e
-            left outer join
-                users_groups_relations as ugr
-                    on ugr.group_id = e.group_id
-                    and ugr.user_id = $1
-            where
-                ugr.group_id is null and e.user_id = $1
-                or ugr.group_id is not null
-            order by e.id"#,
-        user_id.into_inner(),
-    )
-    .fetch_all(pool)
-    .await;
-    let rows = ok_or_log_and_respond_internal_server_error!(rows_result);
 
-    let rest_resources = all_ok_or_log_and_respond_internal_server_error!(rows
-        .iter()
-        .map(|user| user.rest_resource(&request))
-        .collect::<Vec<_>>());
+    // a transaction is used, so all parts of the export are from the same snapshot
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!("set transaction isolation level repeatable read, read only")
+            .execute(&mut *transaction)
+            .await
+    );
+    let user_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            ExportedUser,
+            r#"select id, username, display_name, created, updated from users where (username = $1 or id = $2) and id = $3"#,
+            identifier.as_str(),
+            identifier.parse::<i64>().ok(),
+            user_id, // users can only export their own data
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+    );
+    let Some(user) = user_option else {
+        return HttpResponse::NotFound().json("user not found");
+    };
 
-    HttpResponse::Ok().json(rest_resources)
-}
+    let memberships = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            ExportedMembership,
+            r#"select ugr.group_id, groups.name as group_name, ugr.role as "role: GroupRole", ugr.created as joined
+            from users_groups_relations as ugr
+            inner join groups on groups.id = ugr.group_id
+            where ugr.user_id = $1
+            order by ugr.group_id"#,
+            user_id,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+    );
+    let entries = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Entry,
+            r#"select e.id, e.product, e.amount, e.unit, e.note, e.created, e.bought, e.bought_by, e.deleted, e.deleted_by, e.archive_id, e.trip_id, e.price, e.currency, e.store_id, e.version, e.user_id, e.list_id, l.group_id
+            from entries as e
+            inner join lists as l on l.id = e.list_id
+            where e.user_id = $1
+            order by e.id"#,
+            user_id,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+    );
+    let lists = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            List,
+            r#"select id, name, group_id, user_id, is_default, created from lists where user_id = $1 order by id"#,
+            user_id,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+    );
+    let stores = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Store,
+            r#"select id, name, group_id, user_id, created from stores where user_id = $1 order by id"#,
+            user_id,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+    );
+    let entry_events = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            EntryEvent,
+            r#"select
+                ev.id, ev.entry_id, ev.list_id, l.group_id, ev.actor_id,
+                ev.kind as "kind: EntryEventKind", ev.changes, ev.created
+            from entry_events as ev
+            inner join lists as l on l.id = ev.list_id
+            where ev.actor_id = $1
+            order by ev.id"#,
+            user_id,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+    );
+    let invites = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Invite,
+            r#"select id, group_id, single_use, uses, expires, created_by, created from group_invites where created_by = $1 order by id"#,
+            user_id,
+        )
+        .fetch_all(&mut *transaction)
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
 
-async fn is_member(
-    pool: &Pool<Postgres>,
-    user_id: i64,
-    group_id: i64,
-) -> Result<bool, sqlx::Error> {
-    let is_member_result = sqlx::query!(
-        r#"select exists (select 1 from users_groups_relations where user_id = $1 and group_id = $2) as "exists!: bool""#,
-        user_id,
-        group_id
-    )
-    .fetch_one(pool)
-    .await;
-    Ok(is_member_result?.exists)
-}
+    let filename = format!("shoppinglist-export-{}.json", user.username);
+    let export = UserExport {
+        user,
+        memberships,
+        entries,
+        lists,
+        stores,
+        entry_events,
+        invites,
+        exported: Utc::now(),
+    };
 
-#[derive(Deserialize)]
-pub(super) struct PostEntryRequestData {
-    product: String,
-    amount: f32,
-    unit: String,
-    note: Option<String>,
-    group_id: Option<i64>,
+    HttpResponse::Ok()
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .json(export)
 }
 
-pub(super) async fn post_entry(
+// exchanges the basic credentials for a token,
+// which can then be used as "authorization: bearer <token>"
+pub(super) async fn post_session(
     request: actix_web::HttpRequest,
     app_data: web::Data<AppData>,
     user_id: ReqData<i64>,
-    payload: Json<PostEntryRequestData>,
+    session_id: Option<ReqData<SessionId>>,
+    api_key_scope: Option<ReqData<ApiKeyScope>>,
 ) -> HttpResponse {
+    // otherwise a stolen token could be used to extend the session indefinitely
+    // and a limited api key could be turned into an unlimited session
+    if session_id.is_some() || api_key_scope.is_some() {
+        return HttpResponse::Forbidden()
+            .json("sessions can only be created with basic authentication");
+    }
     let user_id = user_id.into_inner();
     let pool = &app_data.pool;
-    if let Some(group_id) = payload.group_id {
-        let is_member =
-            ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
-        if !is_member {
-            return HttpResponse::NotFound().json("group not found");
-        }
-    }
-    let row_result = sqlx::query_as!(
-        Entry,
-        r#"insert into entries (product, amount, unit, note, user_id, group_id)
-            values ($1, $2, $3, $4, $5, $6)
-        returning id, product, amount, unit, note, user_id, group_id, created, bought"#,
-        payload.product,
-        payload.amount,
-        payload.unit,
-        payload.note,
-        user_id,
-        payload.group_id,
-    )
-    .fetch_one(pool)
-    .await;
-    let row = ok_or_log_and_respond_internal_server_error!(row_result);
 
-    let rest_resource = ok_or_log_and_respond_internal_server_error!(row.rest_resource(&request));
+    // there is no other place where expired sessions are removed
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            "delete from sessions where user_id = $1 and expires <= now()",
+            user_id
+        )
+        .execute(pool)
+        .await
+    );
+
+    let token = token::generate();
+    let session = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Session,
+            r#"insert into sessions (user_id, token_hash, expires) values ($1, $2, $3)
+            returning id, created, expires, last_used"#,
+            user_id,
+            token::hash(&token),
+            Utc::now() + app_data.session_lifetime,
+        )
+        .fetch_one(pool)
+        .await
+    );
+    let new_session = NewSession { session, token };
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(new_session.rest_resource(&request));
 
     HttpResponse::Created().json(rest_resource)
 }
 
-fn deserialize_option_string<'de, D>(input: D) -> Result<Option<Option<String>>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let string_option = Option::<String>::deserialize(input)?;
-    Ok(Some(string_option))
-}
+pub(super) async fn get_sessions(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let sessions = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Session,
+            r#"select id, created, expires, last_used from sessions where user_id = $1 and expires > now() order by id"#,
+            user_id.into_inner(),
+        )
+        .fetch_all(&app_data.pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(sessions
+        .iter()
+        .map(|session| session.rest_resource(&request))
+        .collect::<Vec<_>>());
 
-#[derive(Deserialize, IsEmpty)]
-pub(super) struct PatchEntryRequestData {
-    product: Option<String>,
-    amount: Option<f32>,
-    unit: Option<String>,
-    // the first option shows if a value has been supplied at all
-    // the second option shows if a text or null has been supplied
-    #[serde(default)]
-    #[serde(deserialize_with = "deserialize_option_string")]
-    note: Option<Option<String>>,
-    bought: Option<bool>,
+    HttpResponse::Ok().json(body)
 }
 
-async fn can_modify_entry(
-    pool: &Pool<Postgres>,
-    user_id: i64,
-    entry_id: i64,
-) -> Result<bool, sqlx::Error> {
-    let entry_option = sqlx::query!(
-        r#"select entries.user_id, entries.group_id, case when ugr.group_id is null then false else true end as "is_member!: bool" from entries left outer join users_groups_relations as ugr on entries.group_id = ugr.group_id and ugr.user_id = $1 where entries.id = $2"#,
-        user_id,
-        entry_id
-    )
-    .fetch_optional(pool)
-    .await?;
-    let entry = match entry_option {
-        None => return Ok(false),
-        Some(value) => value,
+pub(super) async fn get_session_by_id(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let session_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Session,
+            r#"select id, created, expires, last_used from sessions where id = $1 and user_id = $2 and expires > now()"#,
+            id.into_inner(),
+            user_id.into_inner(),
+        )
+        .fetch_optional(&app_data.pool)
+        .await
+    );
+    let Some(session) = session_option else {
+        return HttpResponse::NotFound().json("session not found");
     };
-    if entry.group_id.is_none() && entry.user_id != user_id
-        || entry.group_id.is_some() && !entry.is_member
-    {
-        return Ok(false);
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(session.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+// revokes a session, its token can't be used anymore afterwards
+pub(super) async fn delete_session(
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let delete_result = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            "delete from sessions where id = $1 and user_id = $2",
+            id.into_inner(),
+            user_id.into_inner(),
+        )
+        .execute(&app_data.pool)
+        .await
+    );
+    if delete_result.rows_affected() == 0 {
+        return HttpResponse::NotFound().json("session not found");
     }
-    Ok(true)
+
+    HttpResponse::NoContent().finish()
 }
 
-// currently read access and modify access have the same requirements
-// if this later changes the functions that use "can_read_entry" will not need to be changed
-// additionally using "can_read_entry" make it more clear, that we intend to only read an entry
-#[inline(always)]
-async fn can_read_entry(
+// logging out revokes the session the request was authenticated with
+pub(super) async fn delete_current_session(
+    app_data: web::Data<AppData>,
+    session_id: Option<ReqData<SessionId>>,
+) -> HttpResponse {
+    let Some(session_id) = session_id else {
+        return HttpResponse::BadRequest()
+            .json("the request was not authenticated with a session token");
+    };
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!("delete from sessions where id = $1", session_id.0)
+            .execute(&app_data.pool)
+            .await
+    );
+
+    HttpResponse::NoContent().finish()
+}
+
+// checks if the identifier of the path refers to the authenticated user
+async fn is_own_identifier(
     pool: &Pool<Postgres>,
     user_id: i64,
-    entry_id: i64,
+    identifier: &str,
 ) -> Result<bool, sqlx::Error> {
-    can_modify_entry(pool, user_id, entry_id).await
+    if let Ok(id) = identifier.parse::<i64>() {
+        return Ok(id == user_id);
+    }
+    let row = sqlx::query!(
+        r#"select exists (select 1 from users where id = $1 and username = $2) as "exists!: bool""#,
+        user_id,
+        identifier,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(row.exists)
 }
 
-pub(super) async fn patch_entry(
+pub(super) async fn get_api_keys(
     request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
     app_data: web::Data<AppData>,
-    entry_id: web::Path<i64>,
     user_id: ReqData<i64>,
-    payload: Json<PatchEntryRequestData>,
 ) -> HttpResponse {
-    let payload = payload.0;
-    if payload.is_empty() {
-        return HttpResponse::BadRequest().json("specify at least one field!");
-    }
-    let entry_id = entry_id.into_inner();
     let user_id = user_id.into_inner();
     let pool = &app_data.pool;
-    let can_modify_entry = ok_or_log_and_respond_internal_server_error!(
-        can_modify_entry(pool, user_id, entry_id).await
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
     );
-    if !can_modify_entry {
-        return HttpResponse::NotFound().json("entry not found");
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
     }
-    let mut query_builder = QueryBuilder::<Postgres>::new("update entries set ");
-    if let Some(product) = payload.product {
-        query_builder.push(" product = ");
-        query_builder.push_bind(product);
+
+    let api_keys = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            ApiKey,
+            r#"select id, user_id, name, read_only, group_id, expires, created, last_used from api_keys where user_id = $1 order by id"#,
+            user_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(api_keys
+        .iter()
+        .map(|api_key| api_key.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+// same limit as the "name" column of the "api_keys" table
+const API_KEY_NAME_MAX_LENGTH: usize = 80;
+
+#[derive(Deserialize)]
+pub(super) struct PostApiKeyRequestData {
+    name: String,
+    #[serde(default)]
+    read_only: bool,
+    group_id: Option<i64>,
+    expires: Option<DateTime<Utc>>,
+}
+
+pub(super) async fn post_api_key(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    api_key_scope: Option<ReqData<ApiKeyScope>>,
+    payload: Json<PostApiKeyRequestData>,
+) -> HttpResponse {
+    // otherwise a limited api key could be used to create an unlimited one
+    if api_key_scope.is_some() {
+        return HttpResponse::Forbidden().json("api keys can't be used to create api keys");
     }
-    if let Some(value) = payload.amount {
-        query_builder.push(" amount = ");
-        query_builder.push_bind(value);
+    if payload.name.trim().is_empty() || payload.name.chars().count() > API_KEY_NAME_MAX_LENGTH {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {API_KEY_NAME_MAX_LENGTH} characters"
+        ));
     }
-    if let Some(value) = payload.unit {
-        query_builder.push(" unit = ");
-        query_builder.push_bind(value);
+    if payload.expires.is_some_and(|expires| expires <= Utc::now()) {
+        return HttpResponse::BadRequest().json("expires must be in the future");
     }
-    if let Some(value) = payload.note {
-        query_builder.push(" note = ");
-        query_builder.push_bind(value);
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
     }
-    if let Some(value) = payload.bought {
-        query_builder.push(" bought = ");
-        if value {
-            query_builder.push("now()");
-        } else {
-            query_builder.push("null");
+    if let Some(group_id) = payload.group_id {
+        let is_member =
+            ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
+        if !is_member {
+            return HttpResponse::NotFound().json("group not found");
         }
     }
 
-    query_builder.push(" where id = ");
-    query_builder.push_bind(entry_id);
-    query_builder
-        .push(" returning id, product, amount, unit, note, user_id, group_id, created, bought");
+    let key = api_key::generate();
+    let api_key = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            ApiKey,
+            r#"insert into api_keys (user_id, name, key_hash, read_only, group_id, expires)
+                values ($1, $2, $3, $4, $5, $6)
+            returning id, user_id, name, read_only, group_id, expires, created, last_used"#,
+            user_id,
+            payload.name,
+            token::hash(&key),
+            payload.read_only,
+            payload.group_id,
+            payload.expires,
+        )
+        .fetch_one(pool)
+        .await
+    );
+    let new_api_key = NewApiKey { api_key, key };
 
-    let query = query_builder.build_query_as::<Entry>();
-    let entry_result = query.fetch_optional(pool).await;
-    let entry_option = ok_or_log_and_respond_internal_server_error!(entry_result);
-    let entry = match entry_option {
-        Some(entry) => entry,
-        None => {
-            return HttpResponse::NotFound().json("entry not found");
-        }
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(new_api_key.rest_resource(&request));
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+pub(super) async fn get_api_key_by_id(
+    request: actix_web::HttpRequest,
+    path: web::Path<(String, i64)>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let (identifier, api_key_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    let api_key_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            ApiKey,
+            r#"select id, user_id, name, read_only, group_id, expires, created, last_used from api_keys where id = $1 and user_id = $2"#,
+            api_key_id,
+            user_id,
+        )
+        .fetch_optional(pool)
+        .await
+    );
+    let Some(api_key) = api_key_option else {
+        return HttpResponse::NotFound().json("api key not found");
     };
 
-    let rest_resource = ok_or_log_and_respond_internal_server_error!(entry.rest_resource(&request));
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(api_key.rest_resource(&request));
+
     HttpResponse::Ok().json(rest_resource)
 }
 
-pub(super) async fn delete_entry(
+// revokes an api key, it can't be used anymore afterwards
+pub(super) async fn delete_api_key(
+    path: web::Path<(String, i64)>,
     app_data: web::Data<AppData>,
-    entry_id: web::Path<i64>,
     user_id: ReqData<i64>,
 ) -> HttpResponse {
-    let entry_id = entry_id.into_inner();
+    let (identifier, api_key_id) = path.into_inner();
     let user_id = user_id.into_inner();
     let pool = &app_data.pool;
-    let can_modify_entry = ok_or_log_and_respond_internal_server_error!(
-        can_modify_entry(pool, user_id, entry_id).await
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
     );
-    if !can_modify_entry {
-        return HttpResponse::NotFound().json("entry not found");
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
     }
 
-    ok_or_log_and_respond_internal_server_error!(
-        sqlx::query!("delete from entries where id = $1", entry_id)
-            .execute(pool)
-            .await
+    let delete_result = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            "delete from api_keys where id = $1 and user_id = $2",
+            api_key_id,
+            user_id,
+        )
+        .execute(pool)
+        .await
     );
+    if delete_result.rows_affected() == 0 {
+        return HttpResponse::NotFound().json("api key not found");
+    }
 
     HttpResponse::NoContent().finish()
 }
 
-pub(super) async fn get_entry_by_id(
+pub async fn get_groups(
     request: actix_web::HttpRequest,
     app_data: web::Data<AppData>,
-    entry_id: web::Path<i64>,
     user_id: ReqData<i64>,
 ) -> HttpResponse {
-    let entry_id = entry_id.into_inner();
     let user_id = user_id.into_inner();
-    let pool = &app_data.pool;
-    let can_read_entry =
-        ok_or_log_and_respond_internal_server_error!(can_read_entry(pool, user_id, entry_id).await);
-    if !can_read_entry {
-        return HttpResponse::NotFound().json("entry not found");
-    }
+    let rows = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(r#"select id, name, ugr.role as "role: GroupRole" from groups inner join users_groups_relations as ugr on ugr.group_id = id and ugr.user_id = $1 order by id"#, user_id)
+            .fetch_all(&app_data.pool)
+            .await
+    );
+    let groups = rows
+        .into_iter()
+        .map(|row| {
+            (
+                Group {
+                    id: row.id,
+                    name: row.name,
+                },
+                row.role,
+            )
+        })
+        .collect::<Vec<_>>();
+    let rest_resources = all_ok_or_log_and_respond_internal_server_error!(groups
+        .iter()
+        .map(|(item, role)| item.rest_resource(&request, *role))
+        .collect::<Vec<_>>());
 
-    let row_result = sqlx::query_as!(
-        Entry,
-        r#"select
-            e.id, e.product, e.amount, e.unit, e.note, e.created, e.bought, e.user_id, e.group_id
-            from
-                entries as e
-            left outer join
-                users_groups_relations as ugr
-                    on ugr.group_id = e.group_id
-                    and ugr.user_id = $1
-            where
-                (ugr.group_id is null and e.user_id = $1
-                or ugr.group_id is not null)
-                and e.id = $2
-            order by e.id"#,
-        user_id,
-        entry_id,
-    )
-    .fetch_optional(pool)
-    .await;
-    let row_option = ok_or_log_and_respond_internal_server_error!(row_result);
-    let row = match row_option {
-        Some(value) => value,
-        None => return HttpResponse::NotFound().json("entry not found"),
+    respond_with_validators(&request, HttpResponse::Ok(), &rest_resources, None, None)
+}
+
+pub async fn get_group_by_id(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let row_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"select id, name, ugr.role as "role: GroupRole", coalesce(groups.updated, groups.created) as "last_modified!" from groups inner join users_groups_relations as ugr on ugr.group_id = id and ugr.user_id = $1 where id = $2"#,
+            user_id,
+            id.into_inner(),
+        )
+        .fetch_optional(&app_data.pool)
+        .await
+    );
+
+    let Some(row) = row_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    let resource = Group {
+        id: row.id,
+        name: row.name,
     };
 
-    let rest_resource = ok_or_log_and_respond_internal_server_error!(row.rest_resource(&request));
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(resource.rest_resource(&request, row.role));
+
+    respond_with_validators(
+        &request,
+        HttpResponse::Ok(),
+        &rest_resource,
+        None,
+        Some(row.last_modified),
+    )
+}
+
+// same limit as the "name" column of the "groups" table
+const GROUP_NAME_MAX_LENGTH: usize = 80;
+
+fn is_valid_group_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.chars().count() <= GROUP_NAME_MAX_LENGTH
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostGroupRequestData {
+    name: String,
+}
+
+pub(super) async fn post_group(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostGroupRequestData>,
+) -> HttpResponse {
+    if !is_valid_group_name(&payload.name) {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {GROUP_NAME_MAX_LENGTH} characters"
+        ));
+    }
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+
+    // the group and the membership of its creator, who becomes its owner,
+    // are inserted in one transaction,
+    // otherwise a group could exist without any members, which nobody could access
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let group = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Group,
+            r#"insert into groups (name) values ($1) returning id, name"#,
+            payload.name,
+        )
+        .fetch_one(&mut *transaction)
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"insert into users_groups_relations (user_id, group_id, role) values ($1, $2, $3)"#,
+            user_id,
+            group.id,
+            GroupRole::Owner as GroupRole,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(
+        group.rest_resource(&request, GroupRole::Owner)
+    );
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PatchGroupRequestData {
+    name: String,
+}
+
+pub(super) async fn patch_group(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    id: web::Path<i64>,
+    user_id: ReqData<i64>,
+    payload: Json<PatchGroupRequestData>,
+) -> HttpResponse {
+    if !is_valid_group_name(&payload.name) {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {GROUP_NAME_MAX_LENGTH} characters"
+        ));
+    }
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_manage_group() {
+        return HttpResponse::Forbidden().json("only owners and admins can rename the group");
+    }
+
+    let group_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Group,
+            r#"update groups set name = $1 where id = $2 returning id, name"#,
+            payload.name,
+            group_id,
+        )
+        .fetch_optional(pool)
+        .await
+    );
+    // the group could have been deleted between the membership check and the update
+    let Some(group) = group_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(group.rest_resource(&request, role));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+pub(super) async fn delete_group(
+    app_data: web::Data<AppData>,
+    id: web::Path<i64>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_manage_group() {
+        return HttpResponse::Forbidden().json("only owners and admins can delete the group");
+    }
+
+    // memberships and entries of the group are removed by the "on delete cascade" foreign keys
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!("delete from groups where id = $1", group_id)
+            .execute(pool)
+            .await
+    );
+
+    HttpResponse::NoContent().finish()
+}
+
+async fn fetch_group_member<'c>(
+    executor: impl PgExecutor<'c>,
+    group_id: i64,
+    identifier: &str,
+) -> Result<Option<GroupMember>, sqlx::Error> {
+    let member_option = sqlx::query!(
+        r#"select id, username, display_name, ugr.role as "role: GroupRole" from users inner join users_groups_relations as ugr on users.id = ugr.user_id where ugr.group_id = $1 and (username = $2 or id = $3) and id <> $4"#,
+        group_id,
+        identifier,
+        identifier.parse::<i64>().ok(),
+        DELETED_USER_ID,
+    )
+    .fetch_optional(executor)
+    .await?
+    .map(|row| GroupMember {
+        user: User {
+            id: row.id,
+            username: row.username,
+            display_name: row.display_name,
+        },
+        role: row.role,
+    });
+    Ok(member_option)
+}
+
+pub async fn get_group_users(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let is_member = ok_or_log_and_respond_internal_server_error!(
+        is_member(&app_data.pool, user_id, group_id).await
+    );
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+    let rows = ok_or_log_and_respond_internal_server_error!(sqlx::query!(
+        r#"select id, username, display_name, ugr.role as "role: GroupRole" from users inner join users_groups_relations as ugr on users.id = ugr.user_id where ugr.group_id = $1"#,
+        group_id,
+    ).fetch_all(&app_data.pool).await);
+    let members = rows
+        .into_iter()
+        .map(|row| GroupMember {
+            user: User {
+                id: row.id,
+                username: row.username,
+                display_name: row.display_name,
+            },
+            role: row.role,
+        })
+        .collect::<Vec<_>>();
+    let body = all_ok_or_log_and_respond_internal_server_error!(members
+        .iter()
+        .map(|member| member.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    respond_with_validators(&request, HttpResponse::Ok(), &body, None, None)
+}
+
+// users can be referenced by their id or their username,
+// like the "{identifier}" segment of the "/users/{identifier}" resource
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(super) enum UserIdentifier {
+    Id(i64),
+    Username(String),
+}
+
+impl UserIdentifier {
+    // returns the identifier as username and, if possible, as id,
+    // which is the form the queries expect
+    fn as_username_and_id(&self) -> (String, Option<i64>) {
+        match self {
+            UserIdentifier::Id(id) => (id.to_string(), Some(*id)),
+            UserIdentifier::Username(username) => (username.clone(), username.parse::<i64>().ok()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostGroupUserRequestData {
+    identifier: UserIdentifier,
+}
+
+// new members always start with the "member" role,
+// it can be changed afterwards by an owner
+pub(super) async fn post_group_user(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostGroupUserRequestData>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_manage_group() {
+        return HttpResponse::Forbidden().json("only owners and admins can add members");
+    }
+
+    let (username, id_option) = payload.identifier.as_username_and_id();
+    let user_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            User,
+            r#"select id, username, display_name from users where (username = $1 or id = $2) and id <> $3"#,
+            username,
+            id_option,
+            DELETED_USER_ID,
+        )
+        .fetch_optional(pool)
+        .await
+    );
+    // the deleted user only keeps the entries of deleted users, it can't be a member
+    let Some(user) = user_option else {
+        return HttpResponse::NotFound().json("user not found");
+    };
+
+    let insert_result = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"insert into users_groups_relations (user_id, group_id, role) values ($1, $2, $3) on conflict do nothing"#,
+            user.id,
+            group_id,
+            GroupRole::Member as GroupRole,
+        )
+        .execute(pool)
+        .await
+    );
+    if insert_result.rows_affected() == 0 {
+        return HttpResponse::Conflict().json("user is already a member of the group");
+    }
+
+    let member = GroupMember {
+        user,
+        role: GroupRole::Member,
+    };
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(member.rest_resource(&request));
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+pub(super) async fn get_group_user(
+    request: actix_web::HttpRequest,
+    path: web::Path<(i64, String)>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let (group_id, identifier) = path.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_member =
+        ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    let member_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_group_member(pool, group_id, &identifier).await
+    );
+    let Some(member) = member_option else {
+        return HttpResponse::NotFound().json("user not found");
+    };
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(member.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PatchGroupUserRequestData {
+    role: GroupRole,
+}
+
+pub(super) async fn patch_group_user(
+    request: actix_web::HttpRequest,
+    path: web::Path<(i64, String)>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PatchGroupUserRequestData>,
+) -> HttpResponse {
+    let (group_id, identifier) = path.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_change_roles() {
+        return HttpResponse::Forbidden().json("only owners can change the role of members");
+    }
+
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let member_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_group_member(&mut *transaction, group_id, &identifier).await
+    );
+    let Some(mut member) = member_option else {
+        return HttpResponse::NotFound().json("user not found");
+    };
+    if member.role == GroupRole::Owner && payload.role != GroupRole::Owner {
+        let is_last_owner = ok_or_log_and_respond_internal_server_error!(
+            is_last_owner(&mut *transaction, member.user.id, group_id).await
+        );
+        if is_last_owner {
+            return HttpResponse::Conflict()
+                .json("the last owner of a group can't be demoted, promote another member first");
+        }
+    }
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"update users_groups_relations set role = $1 where user_id = $2 and group_id = $3"#,
+            payload.role as GroupRole,
+            member.user.id,
+            group_id,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+    member.role = payload.role;
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(member.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+// removes a member from a group
+// members can also remove themselves, which is how a group is left
+pub(super) async fn delete_group_user(
+    path: web::Path<(i64, String)>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let (group_id, identifier) = path.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let member_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_group_member(&mut *transaction, group_id, &identifier).await
+    );
+    let Some(member) = member_option else {
+        return HttpResponse::NotFound().json("user not found");
+    };
+    let is_leaving = member.user.id == user_id;
+    if !is_leaving && !role.can_remove(member.role) {
+        return HttpResponse::Forbidden().json("insufficient permissions to remove this member");
+    }
+    if member.role == GroupRole::Owner {
+        let is_last_owner = ok_or_log_and_respond_internal_server_error!(
+            is_last_owner(&mut *transaction, member.user.id, group_id).await
+        );
+        let other_members_exist = ok_or_log_and_respond_internal_server_error!(
+            sqlx::query!(
+                r#"select exists (select 1 from users_groups_relations where group_id = $1 and user_id <> $2) as "exists!: bool""#,
+                group_id,
+                member.user.id,
+            )
+            .fetch_one(&mut *transaction)
+            .await
+        )
+        .exists;
+        if is_last_owner && other_members_exist {
+            return HttpResponse::Conflict()
+                .json("the last owner of a group can't leave it, promote another member first");
+        }
+    }
+
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"delete from users_groups_relations where group_id = $1 and user_id = $2"#,
+            group_id,
+            member.user.id,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    // nobody could access a group without members anymore,
+    // so it is deleted together with its last membership
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"delete from groups where id = $1 and not exists (select 1 from users_groups_relations where group_id = $1)"#,
+            group_id,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    HttpResponse::NoContent().finish()
+}
+
+pub(super) async fn get_group_invites(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_manage_group() {
+        return HttpResponse::Forbidden().json("only owners and admins can see invites");
+    }
+
+    let invites = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Invite,
+            r#"select id, group_id, single_use, uses, expires, created_by, created from group_invites where group_id = $1 order by id"#,
+            group_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(invites
+        .iter()
+        .map(|invite| invite.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostGroupInviteRequestData {
+    #[serde(default)]
+    single_use: bool,
+    expires: Option<DateTime<Utc>>,
+}
+
+pub(super) async fn post_group_invite(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostGroupInviteRequestData>,
+) -> HttpResponse {
+    if payload.expires.is_some_and(|expires| expires <= Utc::now()) {
+        return HttpResponse::BadRequest().json("expires must be in the future");
+    }
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_manage_group() {
+        return HttpResponse::Forbidden().json("only owners and admins can invite users");
+    }
+
+    // invite tokens are only known to those the invite url was shared with,
+    // so they have to be unguessable
+    let token = token::generate();
+    let invite = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Invite,
+            r#"insert into group_invites (group_id, token_hash, single_use, expires, created_by)
+                values ($1, $2, $3, $4, $5)
+            returning id, group_id, single_use, uses, expires, created_by, created"#,
+            group_id,
+            token::hash(&token),
+            payload.single_use,
+            payload.expires,
+            user_id,
+        )
+        .fetch_one(pool)
+        .await
+    );
+    let new_invite = NewInvite { invite, token };
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(new_invite.rest_resource(&request));
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+pub(super) async fn get_group_invite(
+    request: actix_web::HttpRequest,
+    path: web::Path<(i64, i64)>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let (group_id, invite_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_manage_group() {
+        return HttpResponse::Forbidden().json("only owners and admins can see invites");
+    }
+
+    let invite_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Invite,
+            r#"select id, group_id, single_use, uses, expires, created_by, created from group_invites where group_id = $1 and id = $2"#,
+            group_id,
+            invite_id,
+        )
+        .fetch_optional(pool)
+        .await
+    );
+    let Some(invite) = invite_option else {
+        return HttpResponse::NotFound().json("invite not found");
+    };
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(invite.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+// revokes an invite, its url can't be used anymore afterwards
+pub(super) async fn delete_group_invite(
+    path: web::Path<(i64, i64)>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let (group_id, invite_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let role_option =
+        ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+    let Some(role) = role_option else {
+        return HttpResponse::NotFound().json("group not found");
+    };
+    if !role.can_manage_group() {
+        return HttpResponse::Forbidden().json("only owners and admins can revoke invites");
+    }
+
+    let delete_result = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            "delete from group_invites where group_id = $1 and id = $2",
+            group_id,
+            invite_id,
+        )
+        .execute(pool)
+        .await
+    );
+    if delete_result.rows_affected() == 0 {
+        return HttpResponse::NotFound().json("invite not found");
+    }
+
+    HttpResponse::NoContent().finish()
+}
+
+// makes the authenticated user a member of the group the invite belongs to
+pub(super) async fn accept_invite(
+    request: actix_web::HttpRequest,
+    token: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    if user_id == DELETED_USER_ID {
+        return HttpResponse::Forbidden().json("the deleted user can't join groups");
+    }
+    let pool = &app_data.pool;
+
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    // expired, used up and revoked invites are all reported as not found,
+    // the client can't do anything about either of them
+    let invite_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"update group_invites set uses = uses + 1
+            where token_hash = $1
+                and (expires is null or expires > now())
+                and (not single_use or uses = 0)
+            returning group_id"#,
+            token::hash(&token),
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+    );
+    let Some(invite) = invite_option else {
+        return HttpResponse::NotFound().json("invite not found");
+    };
+
+    let insert_result = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"insert into users_groups_relations (user_id, group_id, role) values ($1, $2, $3) on conflict do nothing"#,
+            user_id,
+            invite.group_id,
+            GroupRole::Member as GroupRole,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    // the transaction is rolled back when it is dropped,
+    // so the invite is not used up by members accepting it again
+    if insert_result.rows_affected() == 0 {
+        return HttpResponse::Conflict().json("you are already a member of the group");
+    }
+
+    let group = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Group,
+            r#"select id, name from groups where id = $1"#,
+            invite.group_id,
+        )
+        .fetch_one(&mut *transaction)
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(
+        group.rest_resource(&request, GroupRole::Member)
+    );
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+pub async fn get_user_groups_by_id_or_username(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let identifer_string = identifier.into_inner();
+    let user_id = user_id.into_inner();
+    let rows = ok_or_log_and_respond_internal_server_error!(sqlx::query!(
+        r#"select id, name, ugr.role as "role: GroupRole" from groups inner join users_groups_relations as ugr on groups.id = ugr.group_id and ugr.user_id = $1 where ugr.user_id = $2 or ugr.user_id in (select id from users where username = $3)"#,
+        user_id,
+        identifer_string.parse::<i64>().ok(),
+        identifer_string
+    ).fetch_all(&app_data.pool).await);
+    let groups = rows
+        .into_iter()
+        .map(|row| {
+            (
+                Group {
+                    id: row.id,
+                    name: row.name,
+                },
+                row.role,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let body = all_ok_or_log_and_respond_internal_server_error!(groups
+        .iter()
+        .map(|(resource, role)| resource.rest_resource(&request, *role))
+        .collect::<Vec<_>>());
+
+    respond_with_validators(&request, HttpResponse::Ok(), &body, None, None)
+}
+
+const LIST_NAME_MAX_LENGTH: usize = 80;
+
+fn is_valid_list_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.chars().count() <= LIST_NAME_MAX_LENGTH
+}
+
+// returns the list if it is a personal list of the user or a list of one of the user's groups
+async fn fetch_list(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    list_id: i64,
+) -> Result<Option<List>, sqlx::Error> {
+    sqlx::query_as!(
+        List,
+        r#"select l.id, l.name, l.group_id, l.user_id, l.is_default, l.created
+        from lists as l
+        left outer join users_groups_relations as ugr
+            on ugr.group_id = l.group_id
+            and ugr.user_id = $1
+        where l.id = $2 and (l.user_id = $1 or ugr.group_id is not null)"#,
+        user_id,
+        list_id,
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub(super) async fn get_group_lists(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_member =
+        ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    let lists = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            List,
+            r#"select id, name, group_id, user_id, is_default, created from lists where group_id = $1 order by id"#,
+            group_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(lists
+        .iter()
+        .map(|list| list.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostListRequestData {
+    name: String,
+}
+
+// every member of a group can add lists to it, just like entries
+pub(super) async fn post_group_list(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostListRequestData>,
+) -> HttpResponse {
+    if !is_valid_list_name(&payload.name) {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {LIST_NAME_MAX_LENGTH} characters"
+        ));
+    }
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_member =
+        ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    let list = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            List,
+            r#"insert into lists (name, group_id) values ($1, $2)
+            returning id, name, group_id, user_id, is_default, created"#,
+            payload.name,
+            group_id,
+        )
+        .fetch_one(pool)
+        .await
+    );
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(list.rest_resource(&request));
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+pub(super) async fn get_user_lists(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    // personal lists are only visible to their owner
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    let lists = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            List,
+            r#"select id, name, group_id, user_id, is_default, created from lists where user_id = $1 order by id"#,
+            user_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(lists
+        .iter()
+        .map(|list| list.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+pub(super) async fn post_user_list(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostListRequestData>,
+) -> HttpResponse {
+    if !is_valid_list_name(&payload.name) {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {LIST_NAME_MAX_LENGTH} characters"
+        ));
+    }
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    let list = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            List,
+            r#"insert into lists (name, user_id) values ($1, $2)
+            returning id, name, group_id, user_id, is_default, created"#,
+            payload.name,
+            user_id,
+        )
+        .fetch_one(pool)
+        .await
+    );
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(list.rest_resource(&request));
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+pub(super) async fn get_list_by_id(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let list_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_list(&app_data.pool, user_id.into_inner(), id.into_inner()).await
+    );
+    let Some(list) = list_option else {
+        return HttpResponse::NotFound().json("list not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(list.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PatchListRequestData {
+    name: String,
+}
+
+pub(super) async fn patch_list(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PatchListRequestData>,
+) -> HttpResponse {
+    if !is_valid_list_name(&payload.name) {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {LIST_NAME_MAX_LENGTH} characters"
+        ));
+    }
+    let list_id = id.into_inner();
+    let pool = &app_data.pool;
+    let list_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_list(pool, user_id.into_inner(), list_id).await
+    );
+    if list_option.is_none() {
+        return HttpResponse::NotFound().json("list not found");
+    }
+
+    let list_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            List,
+            r#"update lists set name = $1 where id = $2
+            returning id, name, group_id, user_id, is_default, created"#,
+            payload.name,
+            list_id,
+        )
+        .fetch_optional(pool)
+        .await
+    );
+    // the list could have been deleted between the check and the update
+    let Some(list) = list_option else {
+        return HttpResponse::NotFound().json("list not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(list.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+// deleting a list deletes all of its entries,
+// so lists of a group can only be deleted by its owners and admins
+pub(super) async fn delete_list(
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let list_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let list_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_list(pool, user_id, list_id).await);
+    let Some(list) = list_option else {
+        return HttpResponse::NotFound().json("list not found");
+    };
+    if list.is_default {
+        return HttpResponse::Conflict().json("the default list can't be deleted");
+    }
+    if let Some(group_id) = list.group_id {
+        let role_option =
+            ok_or_log_and_respond_internal_server_error!(group_role(pool, user_id, group_id).await);
+        if !role_option.is_some_and(GroupRole::can_manage_group) {
+            return HttpResponse::Forbidden()
+                .json("only owners and admins can delete lists of the group");
+        }
+    }
+
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!("delete from lists where id = $1", list_id)
+            .execute(pool)
+            .await
+    );
+
+    HttpResponse::NoContent().finish()
+}
+
+pub(super) async fn get_list_entries(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetEntriesQuery>,
+) -> HttpResponse {
+    let list_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let list_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_list(pool, user_id, list_id).await);
+    if list_option.is_none() {
+        return HttpResponse::NotFound().json("list not found");
+    }
+
+    respond_with_entries(
+        &request,
+        pool,
+        user_id,
+        Some(EntryScope::List(list_id)),
+        query.into_inner(),
+        (
+            resource_name!("/lists/{id}/entries"),
+            &[list_id.to_string()],
+        ),
+    )
+    .await
+}
+
+// entries can be filtered by the group of their list
+// "personal" selects the entries of personal lists
+pub(super) enum GroupIdFilter {
+    Personal,
+    Group(i64),
+}
+
+impl<'de> Deserialize<'de> for GroupIdFilter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        if value == "personal" {
+            return Ok(GroupIdFilter::Personal);
+        }
+        value
+            .parse::<i64>()
+            .map(GroupIdFilter::Group)
+            .map_err(|_| serde::de::Error::custom("group_id must be a number or \"personal\""))
+    }
+}
+
+// restricts the entries of a collection, in addition to the filters of the query
+#[derive(Clone, Copy)]
+enum EntryScope {
+    Personal,
+    Group(i64),
+    List(i64),
+    // the deleted entries instead of the existing ones
+    Trash,
+    Archive(i64),
+    // the entries bought during a trip, even if they have been archived since
+    Trip(i64),
+}
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum EntryDateField {
+    #[default]
+    Created,
+    Bought,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EntrySortColumn {
+    Id,
+    Product,
+    Amount,
+    Unit,
+    Note,
+    Created,
+    Bought,
+    BoughtBy,
+    Deleted,
+    DeletedBy,
+    Price,
+    UserId,
+    ListId,
+    GroupId,
+}
+
+impl EntrySortColumn {
+    fn from_name(name: &str) -> Option<Self> {
+        let column = match name {
+            "id" => Self::Id,
+            "product" => Self::Product,
+            "amount" => Self::Amount,
+            "unit" => Self::Unit,
+            "note" => Self::Note,
+            "created" => Self::Created,
+            "bought" => Self::Bought,
+            "bought_by" => Self::BoughtBy,
+            "deleted" => Self::Deleted,
+            "deleted_by" => Self::DeletedBy,
+            "price" => Self::Price,
+            "user_id" => Self::UserId,
+            "list_id" => Self::ListId,
+            "group_id" => Self::GroupId,
+            _ => return None,
+        };
+        Some(column)
+    }
+
+    // the column names are never taken from the request,
+    // so they can be pushed into the query without binding them
+    fn column(self) -> &'static str {
+        match self {
+            Self::Id => "e.id",
+            Self::Product => "e.product",
+            Self::Amount => "e.amount",
+            Self::Unit => "e.unit",
+            Self::Note => "e.note",
+            Self::Created => "e.created",
+            Self::Bought => "e.bought",
+            Self::BoughtBy => "e.bought_by",
+            Self::Deleted => "e.deleted",
+            Self::DeletedBy => "e.deleted_by",
+            Self::Price => "e.price",
+            Self::UserId => "e.user_id",
+            Self::ListId => "e.list_id",
+            Self::GroupId => "l.group_id",
+        }
+    }
+
+    // the inverse of "value", the values of cursors are parsed before they are bound,
+    // so a tampered cursor is rejected instead of failing the query
+    fn parse_value(self, value: &str) -> Option<CursorValue> {
+        let value = match self {
+            Self::Id
+            | Self::BoughtBy
+            | Self::DeletedBy
+            | Self::UserId
+            | Self::ListId
+            | Self::GroupId => CursorValue::BigInt(value.parse().ok()?),
+            Self::Product | Self::Unit | Self::Note => CursorValue::Text(value.to_string()),
+            Self::Amount => CursorValue::Real(value.parse().ok()?),
+            Self::Price => CursorValue::Numeric(value.parse().ok()?),
+            Self::Created | Self::Bought | Self::Deleted => {
+                CursorValue::Timestamp(DateTime::parse_from_rfc3339(value).ok()?.to_utc())
+            }
+        };
+        Some(value)
+    }
+
+    fn value(self, entry: &Entry) -> Option<String> {
+        match self {
+            Self::Id => Some(entry.id.to_string()),
+            Self::Product => Some(entry.product.clone()),
+            Self::Amount => Some(entry.amount.to_string()),
+            Self::Unit => Some(entry.unit.clone()),
+            Self::Note => entry.note.clone(),
+            Self::Created => Some(entry.created.to_rfc3339()),
+            Self::Bought => entry.bought.map(|bought| bought.to_rfc3339()),
+            Self::BoughtBy => entry.bought_by.map(|bought_by| bought_by.to_string()),
+            Self::Deleted => entry.deleted.map(|deleted| deleted.to_rfc3339()),
+            Self::DeletedBy => entry.deleted_by.map(|deleted_by| deleted_by.to_string()),
+            Self::Price => entry.price.map(|price| price.to_string()),
+            Self::UserId => Some(entry.user_id.to_string()),
+            Self::ListId => Some(entry.list_id.to_string()),
+            Self::GroupId => entry.group_id.map(|group_id| group_id.to_string()),
+        }
+    }
+}
+
+// "product" sorts ascending, "-product" descending
+struct EntrySort {
+    column: EntrySortColumn,
+    descending: bool,
+}
+
+impl EntrySort {
+    fn parse(sort: &str) -> Option<Self> {
+        let (name, descending) = match sort.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (sort, false),
+        };
+        Some(EntrySort {
+            column: EntrySortColumn::from_name(name)?,
+            descending,
+        })
+    }
+}
+
+// the value of the sort column of the last entry of a page, in the type of the column
+#[derive(Clone, Debug, PartialEq)]
+enum CursorValue {
+    BigInt(i64),
+    Text(String),
+    Real(f32),
+    Numeric(Decimal),
+    Timestamp(DateTime<Utc>),
+}
+
+impl CursorValue {
+    fn push_bind(self, query_builder: &mut QueryBuilder<'_, Postgres>) {
+        match self {
+            CursorValue::BigInt(value) => query_builder.push_bind(value),
+            CursorValue::Text(value) => query_builder.push_bind(value),
+            CursorValue::Real(value) => query_builder.push_bind(value),
+            CursorValue::Numeric(value) => query_builder.push_bind(value),
+            CursorValue::Timestamp(value) => query_builder.push_bind(value),
+        };
+    }
+}
+
+// points to the last entry of a page, the next page starts after it
+// the sort is part of the cursor, so it can't be used with a different one
+#[derive(Serialize, Deserialize)]
+struct EntriesCursor {
+    sort: String,
+    value: Option<String>,
+    id: i64,
+}
+
+// a cursor that has been checked against the sort of the request
+#[derive(Debug, PartialEq)]
+struct DecodedEntriesCursor {
+    value: Option<CursorValue>,
+    id: i64,
+}
+
+impl EntriesCursor {
+    fn encode(&self) -> Result<String, serde_json::Error> {
+        Ok(URL_SAFE_NO_PAD.encode(serde_json::to_vec(self)?))
+    }
+
+    // returns None if the cursor is malformed, belongs to another sort
+    // or its value doesn't fit the type of the sort column
+    fn decode(cursor: &str, sort: &str, column: EntrySortColumn) -> Option<DecodedEntriesCursor> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let cursor: EntriesCursor = serde_json::from_slice(&bytes).ok()?;
+        if cursor.sort != sort {
+            return None;
+        }
+        let value = match cursor.value {
+            Some(value) => Some(column.parse_value(&value)?),
+            None => None,
+        };
+        Some(DecodedEntriesCursor {
+            value,
+            id: cursor.id,
+        })
+    }
+}
+
+// only used once the client paginates, a request without a limit and a cursor gets all entries,
+// like before pagination existed
+const ENTRIES_DEFAULT_LIMIT: i64 = 100;
+const ENTRIES_MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize)]
+pub(super) struct GetEntriesQuery {
+    bought: Option<bool>,
+    group_id: Option<GroupIdFilter>,
+    list_id: Option<i64>,
+    created_by: Option<i64>,
+    bought_by: Option<i64>,
+    // case insensitive substring of the product
+    product: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    // the column that since and until apply to
+    #[serde(default)]
+    date_field: EntryDateField,
+    sort: Option<String>,
+    limit: Option<i64>,
+    cursor: Option<String>,
+}
+
+// generates the value of the link header that points to the next page of a collection
+// the other parameters of the request are kept, so the next page has the same filters
+fn next_page_link(
+    request: &actix_web::HttpRequest,
+    (resource_name, elements): (&str, &[String]),
+    cursor_key: &str,
+    cursor: &str,
+) -> Result<String, actix_web::error::UrlGenerationError> {
+    let mut next_url = request.url_for(resource_name, elements).inspect_err(|_| {
+        log::error!("Failed to get url for resource name: {}", resource_name);
+    })?;
+    next_url.set_query(Some(request.query_string()));
+    let pairs = next_url
+        .query_pairs()
+        .filter(|(key, _)| key != cursor_key)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    next_url
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(cursor_key, cursor);
+    Ok(format!("<{next_url}>; rel=\"next\""))
+}
+
+// escapes the wildcards of "like", so the value is matched literally
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub async fn get_entries(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetEntriesQuery>,
+) -> HttpResponse {
+    respond_with_entries(
+        &request,
+        &app_data.pool,
+        user_id.into_inner(),
+        None,
+        query.into_inner(),
+        (resource_name!("/entries"), &[]),
+    )
+    .await
+}
+
+pub(super) async fn get_group_entries(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetEntriesQuery>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_member =
+        ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    respond_with_entries(
+        &request,
+        pool,
+        user_id,
+        Some(EntryScope::Group(group_id)),
+        query.into_inner(),
+        (
+            resource_name!("/groups/{id}/entries"),
+            &[group_id.to_string()],
+        ),
+    )
+    .await
+}
+
+pub(super) async fn get_user_entries(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetEntriesQuery>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    // personal entries are only visible to their creator
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    respond_with_entries(
+        &request,
+        pool,
+        user_id,
+        Some(EntryScope::Personal),
+        query.into_inner(),
+        (
+            resource_name!("/users/{identifier}/entries"),
+            &[identifier.into_inner()],
+        ),
+    )
+    .await
+}
+
+// the deleted entries the user could restore, until they are purged
+pub(super) async fn get_trash(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetEntriesQuery>,
+) -> HttpResponse {
+    respond_with_entries(
+        &request,
+        &app_data.pool,
+        user_id.into_inner(),
+        Some(EntryScope::Trash),
+        query.into_inner(),
+        (resource_name!("/entries/trash"), &[]),
+    )
+    .await
+}
+
+// responds with a page of the entries visible to the user
+// scope restricts the entries in addition to the filters of the query
+// the url of the next page is generated from next_page_resource
+async fn respond_with_entries(
+    request: &actix_web::HttpRequest,
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    scope: Option<EntryScope>,
+    query: GetEntriesQuery,
+    next_page_resource: (&str, &[String]),
+) -> HttpResponse {
+    let sort_string = query.sort.as_deref().unwrap_or("id");
+    let Some(sort) = EntrySort::parse(sort_string) else {
+        return HttpResponse::BadRequest()
+            .json("sort must be the name of a column, optionally prefixed with '-'");
+    };
+    let limit_option = match (query.limit, &query.cursor) {
+        (None, None) => None,
+        (limit, _) => Some(limit.unwrap_or(ENTRIES_DEFAULT_LIMIT)),
+    };
+    if let Some(limit) = limit_option {
+        if !(1..=ENTRIES_MAX_LIMIT).contains(&limit) {
+            return HttpResponse::BadRequest()
+                .json(format!("limit must be between 1 and {ENTRIES_MAX_LIMIT}"));
+        }
+    }
+    let cursor_option = match query.cursor.as_deref() {
+        None => None,
+        Some(cursor) => match EntriesCursor::decode(cursor, sort_string, sort.column) {
+            Some(cursor) => Some(cursor),
+            None => return HttpResponse::BadRequest().json("invalid cursor"),
+        },
+    };
+
+    // only show entries of groups the user is a member of
+    // or their personal entries
+    // this does not show entries that were created by the user
+    // but the user is not part of the assigned group anymore
+    // this is intentional!
+    let mut query_builder = QueryBuilder::<Postgres>::new(
+        r#"select
+            e.id, e.product, e.amount, e.unit, e.note, e.created, e.bought, e.bought_by, e.deleted, e.deleted_by, e.archive_id, e.trip_id, e.price, e.currency, e.store_id, e.version, e.user_id, e.list_id, l.group_id
+            from
+                entries as e
+            inner join
+                lists as l
+                    on l.id = e.list_id
+            left outer join
+                users_groups_relations as ugr
+                    on ugr.group_id = l.group_id
+                    and ugr.user_id = "#,
+    );
+    query_builder.push_bind(user_id);
+    query_builder.push(" where (l.user_id = ");
+    query_builder.push_bind(user_id);
+    query_builder.push(" or ugr.group_id is not null)");
+
+    if let Some(bought) = query.bought {
+        if bought {
+            query_builder.push(" and e.bought is not null");
+        } else {
+            query_builder.push(" and e.bought is null");
+        }
+    }
+    // the trash is the only collection that shows deleted entries
+    if matches!(scope, Some(EntryScope::Trash)) {
+        query_builder.push(" and e.deleted is not null");
+    } else {
+        query_builder.push(" and e.deleted is null");
+    }
+    // archived entries are only shown in their archive and the trash
+    match scope {
+        Some(EntryScope::Archive(archive_id)) => {
+            query_builder.push(" and e.archive_id = ");
+            query_builder.push_bind(archive_id);
+        }
+        Some(EntryScope::Trip(trip_id)) => {
+            query_builder.push(" and e.trip_id = ");
+            query_builder.push_bind(trip_id);
+        }
+        Some(EntryScope::Trash) => {}
+        _ => {
+            query_builder.push(" and e.archive_id is null");
+        }
+    }
+    match scope {
+        None
+        | Some(EntryScope::Trash)
+        | Some(EntryScope::Archive(_))
+        | Some(EntryScope::Trip(_)) => {}
+        Some(EntryScope::Personal) => {
+            query_builder.push(" and l.group_id is null");
+        }
+        Some(EntryScope::Group(group_id)) => {
+            query_builder.push(" and l.group_id = ");
+            query_builder.push_bind(group_id);
+        }
+        Some(EntryScope::List(list_id)) => {
+            query_builder.push(" and e.list_id = ");
+            query_builder.push_bind(list_id);
+        }
+    }
+    match query.group_id {
+        None => {}
+        Some(GroupIdFilter::Personal) => {
+            query_builder.push(" and l.group_id is null");
+        }
+        Some(GroupIdFilter::Group(group_id)) => {
+            query_builder.push(" and l.group_id = ");
+            query_builder.push_bind(group_id);
+        }
+    }
+    if let Some(list_id) = query.list_id {
+        query_builder.push(" and e.list_id = ");
+        query_builder.push_bind(list_id);
+    }
+    if let Some(created_by) = query.created_by {
+        query_builder.push(" and e.user_id = ");
+        query_builder.push_bind(created_by);
+    }
+    if let Some(bought_by) = query.bought_by {
+        query_builder.push(" and e.bought_by = ");
+        query_builder.push_bind(bought_by);
+    }
+    if let Some(product) = query.product {
+        query_builder.push(" and e.product ilike '%' || ");
+        query_builder.push_bind(escape_like(&product));
+        query_builder.push(" || '%'");
+    }
+    let date_column = match query.date_field {
+        EntryDateField::Created => "e.created",
+        EntryDateField::Bought => "e.bought",
+    };
+    if let Some(since) = query.since {
+        query_builder.push(format_args!(" and {date_column} >= "));
+        query_builder.push_bind(since);
+    }
+    if let Some(until) = query.until {
+        query_builder.push(format_args!(" and {date_column} < "));
+        query_builder.push_bind(until);
+    }
+
+    // keyset pagination: the page starts after the entry of the cursor
+    // null values are always sorted last and the id breaks ties
+    let column = sort.column.column();
+    let (comparison, direction) = if sort.descending {
+        ("<", "desc")
+    } else {
+        (">", "asc")
+    };
+    if let Some(cursor) = cursor_option {
+        match cursor.value {
+            Some(value) => {
+                query_builder.push(format_args!(" and ({column} {comparison} "));
+                value.clone().push_bind(&mut query_builder);
+                query_builder.push(format_args!(" or ({column} = "));
+                value.push_bind(&mut query_builder);
+                query_builder.push(format_args!(" and e.id {comparison} "));
+                query_builder.push_bind(cursor.id);
+                query_builder.push(format_args!(") or {column} is null)"));
+            }
+            None => {
+                query_builder.push(format_args!(
+                    " and ({column} is null and e.id {comparison} "
+                ));
+                query_builder.push_bind(cursor.id);
+                query_builder.push(")");
+            }
+        }
+    }
+    query_builder.push(format_args!(
+        " order by {column} {direction} nulls last, e.id {direction}"
+    ));
+    if let Some(limit) = limit_option {
+        // one more than requested, to know if there is a next page
+        query_builder.push(" limit ");
+        query_builder.push_bind(limit + 1);
+    }
+
+    let mut rows = ok_or_log_and_respond_internal_server_error!(
+        query_builder
+            .build_query_as::<Entry>()
+            .fetch_all(pool)
+            .await
+    );
+
+    let mut response_builder = HttpResponse::Ok();
+    if let Some(limit) = limit_option.filter(|limit| rows.len() as i64 > *limit) {
+        rows.truncate(limit as usize);
+        // there are more rows than the limit, so there is a last entry
+        let last = &rows[rows.len() - 1];
+        let next_cursor = EntriesCursor {
+            sort: sort_string.to_string(),
+            value: sort.column.value(last),
+            id: last.id,
+        };
+        let encoded_cursor = ok_or_log_and_respond_internal_server_error!(next_cursor.encode());
+        let link = ok_or_log_and_respond_internal_server_error!(next_page_link(
+            request,
+            next_page_resource,
+            "cursor",
+            &encoded_cursor
+        ));
+        response_builder.insert_header((header::LINK, link));
+    }
+
+    let rest_resources = all_ok_or_log_and_respond_internal_server_error!(rows
+        .iter()
+        .map(|entry| entry.rest_resource(request))
+        .collect::<Vec<_>>());
+
+    respond_with_validators(request, response_builder, &rest_resources, None, None)
+}
+
+async fn is_member(
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    group_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let is_member_result = sqlx::query!(
+        r#"select exists (select 1 from users_groups_relations where user_id = $1 and group_id = $2) as "exists!: bool""#,
+        user_id,
+        group_id
+    )
+    .fetch_one(pool)
+    .await;
+    Ok(is_member_result?.exists)
+}
+
+// returns None if the user is not a member of the group
+async fn group_role(
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    group_id: i64,
+) -> Result<Option<GroupRole>, sqlx::Error> {
+    let row_option = sqlx::query!(
+        r#"select role as "role: GroupRole" from users_groups_relations where user_id = $1 and group_id = $2"#,
+        user_id,
+        group_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row_option.map(|row| row.role))
+}
+
+// the owners are locked until the transaction ends, otherwise two owners could demote
+// or remove each other at the same time, both seeing the other one as the remaining owner
+async fn is_last_owner<'c>(
+    executor: impl PgExecutor<'c>,
+    user_id: i64,
+    group_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let owner_ids = sqlx::query_scalar!(
+        r#"select user_id from users_groups_relations where group_id = $1 and role = 'owner' order by user_id for update"#,
+        group_id,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(owner_ids.iter().all(|owner_id| *owner_id == user_id))
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostEntryRequestData {
+    product: String,
+    amount: f32,
+    unit: String,
+    note: Option<String>,
+    // without a list the default list of the group is used,
+    // or the personal default list if there is no group either
+    group_id: Option<i64>,
+    list_id: Option<i64>,
+}
+
+pub(super) async fn post_entry(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostEntryRequestData>,
+) -> HttpResponse {
+    insert_entry(
+        &request,
+        &app_data.pool,
+        user_id.into_inner(),
+        payload.into_inner(),
+        None,
+    )
+    .await
+}
+
+pub(super) async fn post_group_entry(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostEntryRequestData>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let payload = payload.into_inner();
+    if payload
+        .group_id
+        .is_some_and(|payload_group_id| payload_group_id != group_id)
+    {
+        return HttpResponse::BadRequest().json("group_id does not match the group of the url");
+    }
+    insert_entry(
+        &request,
+        &app_data.pool,
+        user_id.into_inner(),
+        payload,
+        Some(EntryScope::Group(group_id)),
+    )
+    .await
+}
+
+pub(super) async fn post_user_entry(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostEntryRequestData>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+    let payload = payload.into_inner();
+    if payload.group_id.is_some() {
+        return HttpResponse::BadRequest().json("personal entries can't have a group_id");
+    }
+    insert_entry(&request, pool, user_id, payload, Some(EntryScope::Personal)).await
+}
+
+pub(super) async fn post_list_entry(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostEntryRequestData>,
+) -> HttpResponse {
+    let list_id = id.into_inner();
+    let mut payload = payload.into_inner();
+    if payload
+        .list_id
+        .is_some_and(|payload_list_id| payload_list_id != list_id)
+    {
+        return HttpResponse::BadRequest().json("list_id does not match the list of the url");
+    }
+    payload.list_id = Some(list_id);
+    insert_entry(
+        &request,
+        &app_data.pool,
+        user_id.into_inner(),
+        payload,
+        Some(EntryScope::List(list_id)),
+    )
+    .await
+}
+
+// finds the list a new entry is added to
+// returns none if the list does not exist, the user can't access it or it is outside of the scope
+async fn find_list_for_new_entry(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    payload: &PostEntryRequestData,
+    scope: Option<EntryScope>,
+) -> Result<Option<i64>, sqlx::Error> {
+    let mut query_builder = QueryBuilder::<Postgres>::new(
+        r#"select l.id
+            from
+                lists as l
+            left outer join
+                users_groups_relations as ugr
+                    on ugr.group_id = l.group_id
+                    and ugr.user_id = "#,
+    );
+    query_builder.push_bind(user_id);
+    query_builder.push(" where (l.user_id = ");
+    query_builder.push_bind(user_id);
+    query_builder.push(" or ugr.group_id is not null)");
+
+    match payload.list_id {
+        Some(list_id) => {
+            query_builder.push(" and l.id = ");
+            query_builder.push_bind(list_id);
+        }
+        None => {
+            query_builder.push(" and l.is_default");
+        }
+    }
+    if let Some(group_id) = payload.group_id {
+        query_builder.push(" and l.group_id = ");
+        query_builder.push_bind(group_id);
+    }
+    match scope {
+        Some(EntryScope::Personal) => {
+            query_builder.push(" and l.group_id is null");
+        }
+        Some(EntryScope::Group(group_id)) => {
+            query_builder.push(" and l.group_id = ");
+            query_builder.push_bind(group_id);
+        }
+        Some(EntryScope::List(list_id)) => {
+            query_builder.push(" and l.id = ");
+            query_builder.push_bind(list_id);
+        }
+        // entries can't be created in the trash, an archive or a trip
+        Some(EntryScope::Trash) | Some(EntryScope::Archive(_)) | Some(EntryScope::Trip(_)) => {
+            return Ok(None);
+        }
+        // without any hint the personal default list is used
+        None if payload.list_id.is_none() && payload.group_id.is_none() => {
+            query_builder.push(" and l.group_id is null");
+        }
+        None => {}
+    }
+
+    let row_option = query_builder
+        .build_query_scalar::<i64>()
+        .fetch_optional(executor)
+        .await?;
+    Ok(row_option)
+}
+
+// the reasons why creating, patching or deleting an entry can fail
+// they are shared by the single endpoints and the batch endpoint
+enum EntryOperationError {
+    BadRequest(&'static str),
+    Forbidden(&'static str),
+    NotFound(&'static str),
+    Conflict(&'static str),
+    // the entry doesn't match the if-match header anymore, it contains the current entry
+    PreconditionFailed(Box<Entry>),
+    Database(sqlx::Error),
+}
+
+impl From<sqlx::Error> for EntryOperationError {
+    fn from(err: sqlx::Error) -> Self {
+        EntryOperationError::Database(err)
+    }
+}
+
+impl EntryOperationError {
+    fn status(&self) -> StatusCode {
+        match self {
+            EntryOperationError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            EntryOperationError::Forbidden(_) => StatusCode::FORBIDDEN,
+            EntryOperationError::NotFound(_) => StatusCode::NOT_FOUND,
+            EntryOperationError::Conflict(_) => StatusCode::CONFLICT,
+            EntryOperationError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            EntryOperationError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    // errors of the database are logged and not shown to the client
+    fn message(&self) -> &'static str {
+        match self {
+            EntryOperationError::BadRequest(message)
+            | EntryOperationError::Forbidden(message)
+            | EntryOperationError::NotFound(message)
+            | EntryOperationError::Conflict(message) => message,
+            EntryOperationError::PreconditionFailed(_) => {
+                "the entry has been changed since the version of the if-match header"
+            }
+            EntryOperationError::Database(err) => {
+                log::error!("Internal server error: {}", err);
+                "internal server error"
+            }
+        }
+    }
+
+    fn into_response(self) -> HttpResponse {
+        HttpResponseBuilder::new(self.status()).json(self.message())
+    }
+
+    // the entry as it is now, if the precondition failed
+    fn current_entry(&self) -> Option<&Entry> {
+        match self {
+            EntryOperationError::PreconditionFailed(entry) => Some(entry),
+            _ => None,
+        }
+    }
+
+    // the current entry is part of the response if the precondition failed,
+    // so the client can merge its changes without fetching it again
+    fn into_entry_response(self, request: &actix_web::HttpRequest) -> HttpResponse {
+        let EntryOperationError::PreconditionFailed(entry) = self else {
+            return self.into_response();
+        };
+        let rest_resource =
+            ok_or_log_and_respond_internal_server_error!(entry.rest_resource(request));
+        HttpResponse::PreconditionFailed()
+            .insert_header(header::ETag(entry.etag()))
+            .json(rest_resource)
+    }
+}
+
+// an archive only holds the entries that were bought when it was created,
+// so they can't be changed or deleted anymore
+fn check_entry_not_archived(entry: &Entry) -> Result<(), EntryOperationError> {
+    if entry.archive_id.is_some() {
+        return Err(EntryOperationError::Conflict(
+            "archived entries can't be changed",
+        ));
+    }
+    Ok(())
+}
+
+// the entry has to be locked, so it can't change between the check and the modification
+// without the header every version matches
+fn check_entry_precondition(
+    entry: &Entry,
+    if_match: Option<&header::IfMatch>,
+) -> Result<(), EntryOperationError> {
+    let matches = match if_match {
+        None | Some(header::IfMatch::Any) => true,
+        Some(header::IfMatch::Items(etags)) => {
+            let etag = entry.etag();
+            etags.iter().any(|item| item.strong_eq(&etag))
+        }
+    };
+    if !matches {
+        return Err(EntryOperationError::PreconditionFailed(Box::new(
+            entry.clone(),
+        )));
+    }
+    Ok(())
+}
+
+// the user needs access to the list of the entry, see "find_list_for_new_entry"
+async fn create_entry(
+    connection: &mut PgConnection,
+    user_id: i64,
+    payload: PostEntryRequestData,
+    scope: Option<EntryScope>,
+) -> Result<Entry, EntryOperationError> {
+    let list_id_option =
+        find_list_for_new_entry(&mut *connection, user_id, &payload, scope).await?;
+    let Some(list_id) = list_id_option else {
+        if payload.list_id.is_some() || matches!(scope, Some(EntryScope::List(_))) {
+            return Err(EntryOperationError::NotFound("list not found"));
+        }
+        return Err(EntryOperationError::NotFound("group not found"));
+    };
+    let entry = sqlx::query_as!(
+        Entry,
+        r#"insert into entries (product, amount, unit, note, user_id, list_id)
+            values ($1, $2, $3, $4, $5, $6)
+        returning id, product, amount, unit, note, user_id, list_id,
+            (select group_id from lists where lists.id = entries.list_id) as group_id,
+            created, bought, bought_by, deleted, deleted_by, archive_id, trip_id, price, currency, store_id, version"#,
+        payload.product,
+        payload.amount,
+        payload.unit,
+        payload.note,
+        user_id,
+        list_id,
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+    record_entry_event(
+        &mut *connection,
+        user_id,
+        EntryEventKind::Created,
+        None,
+        Some(&entry),
+    )
+    .await?;
+    Ok(entry)
+}
+
+async fn insert_entry(
+    request: &actix_web::HttpRequest,
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    payload: PostEntryRequestData,
+    scope: Option<EntryScope>,
+) -> HttpResponse {
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let entry = match create_entry(&mut transaction, user_id, payload, scope).await {
+        Ok(entry) => entry,
+        Err(err) => return err.into_response(),
+    };
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(entry.rest_resource(request));
+
+    HttpResponse::Created()
+        .insert_header(header::ETag(entry.etag()))
+        .json(rest_resource)
+}
+
+fn deserialize_nullable<'de, D, T>(input: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let value_option = Option::<T>::deserialize(input)?;
+    Ok(Some(value_option))
+}
+
+#[derive(Deserialize, IsEmpty)]
+pub(super) struct PatchEntryRequestData {
+    product: Option<String>,
+    amount: Option<f32>,
+    unit: Option<String>,
+    // the first option shows if a value has been supplied at all
+    // the second option shows if a text or null has been supplied
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_nullable")]
+    note: Option<Option<String>>,
+    bought: Option<bool>,
+    // can only be set for bought entries and is removed when the entry is not bought anymore
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_nullable")]
+    price: Option<Option<Decimal>>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_nullable")]
+    currency: Option<Option<String>>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_nullable")]
+    store_id: Option<Option<i64>>,
+}
+
+// the fields of an entry that are part of its history
+const TRACKED_ENTRY_FIELDS: [&str; 9] = [
+    "product",
+    "amount",
+    "unit",
+    "note",
+    "bought",
+    "bought_by",
+    "price",
+    "currency",
+    "store_id",
+];
+
+// the values of the tracked fields, in the same order
+// all fields of a missing entry are null, so created and deleted entries show all their values
+fn tracked_entry_values(entry: Option<&Entry>) -> [serde_json::Value; 9] {
+    let Some(entry) = entry else {
+        return Default::default();
+    };
+    [
+        json!(entry.product),
+        json!(entry.amount),
+        json!(entry.unit),
+        json!(entry.note),
+        json!(entry.bought),
+        json!(entry.bought_by),
+        json!(entry.price),
+        json!(entry.currency),
+        json!(entry.store_id),
+    ]
+}
+
+// maps every changed field to its old and new value
+fn entry_changes(old: Option<&Entry>, new: Option<&Entry>) -> serde_json::Value {
+    let mut changes = serde_json::Map::new();
+    let values = tracked_entry_values(old)
+        .into_iter()
+        .zip(tracked_entry_values(new));
+    for (field, (old_value, new_value)) in TRACKED_ENTRY_FIELDS.iter().zip(values) {
+        if old_value != new_value {
+            changes.insert(
+                field.to_string(),
+                json!({ "old": old_value, "new": new_value }),
+            );
+        }
+    }
+    serde_json::Value::Object(changes)
+}
+
+// should be executed in the same transaction as the change itself,
+// so the history can't miss any change
+async fn record_entry_event(
+    executor: impl PgExecutor<'_>,
+    actor_id: i64,
+    kind: EntryEventKind,
+    old: Option<&Entry>,
+    new: Option<&Entry>,
+) -> Result<(), sqlx::Error> {
+    let Some(entry) = new.or(old) else {
+        return Ok(());
+    };
+    sqlx::query!(
+        r#"insert into entry_events (entry_id, list_id, actor_id, kind, changes) values ($1, $2, $3, $4, $5)"#,
+        entry.id,
+        entry.list_id,
+        actor_id,
+        kind as EntryEventKind,
+        entry_changes(old, new),
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// locks the entry until the end of the transaction
+async fn fetch_entry_for_update(
+    executor: impl PgExecutor<'_>,
+    entry_id: i64,
+) -> Result<Option<Entry>, sqlx::Error> {
+    sqlx::query_as!(
+        Entry,
+        r#"select e.id, e.product, e.amount, e.unit, e.note, e.created, e.bought, e.bought_by, e.deleted, e.deleted_by, e.archive_id, e.trip_id, e.price, e.currency, e.store_id, e.version, e.user_id, e.list_id, l.group_id
+        from entries as e
+        inner join lists as l on l.id = e.list_id
+        where e.id = $1
+        for update of e"#,
+        entry_id,
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// in_trash selects if the entry has to be deleted or not
+async fn has_entry_access(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    entry_id: i64,
+    in_trash: bool,
+) -> Result<bool, sqlx::Error> {
+    let entry_option = sqlx::query!(
+        r#"select l.user_id, l.group_id, case when ugr.group_id is null then false else true end as "is_member!: bool" from entries inner join lists as l on l.id = entries.list_id left outer join users_groups_relations as ugr on l.group_id = ugr.group_id and ugr.user_id = $1 where entries.id = $2 and (entries.deleted is not null) = $3"#,
+        user_id,
+        entry_id,
+        in_trash,
+    )
+    .fetch_optional(executor)
+    .await?;
+    let entry = match entry_option {
+        None => return Ok(false),
+        Some(value) => value,
+    };
+    // the user_id is the owner of the list, not the creator of the entry
+    if entry.group_id.is_none() && entry.user_id != Some(user_id)
+        || entry.group_id.is_some() && !entry.is_member
+    {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+// deleted entries can't be modified, only restored
+async fn can_modify_entry(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    entry_id: i64,
+) -> Result<bool, sqlx::Error> {
+    has_entry_access(executor, user_id, entry_id, false).await
+}
+
+// everyone that could modify an entry before it was deleted can restore it
+async fn can_restore_entry(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    entry_id: i64,
+) -> Result<bool, sqlx::Error> {
+    has_entry_access(executor, user_id, entry_id, true).await
+}
+
+// currently read access and modify access have the same requirements
+// if this later changes the functions that use "can_read_entry" will not need to be changed
+// additionally using "can_read_entry" make it more clear, that we intend to only read an entry
+#[inline(always)]
+async fn can_read_entry(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    entry_id: i64,
+) -> Result<bool, sqlx::Error> {
+    can_modify_entry(executor, user_id, entry_id).await
+}
+
+async fn update_entry(
+    connection: &mut PgConnection,
+    user_id: i64,
+    entry_id: i64,
+    payload: PatchEntryRequestData,
+    if_match: Option<&header::IfMatch>,
+) -> Result<Entry, EntryOperationError> {
+    if payload.is_empty() {
+        return Err(EntryOperationError::BadRequest(
+            "specify at least one field!",
+        ));
+    }
+    if !can_modify_entry(&mut *connection, user_id, entry_id).await? {
+        return Err(EntryOperationError::NotFound("entry not found"));
+    }
+
+    // the old state of the entry is needed for its history
+    let old_entry_option = fetch_entry_for_update(&mut *connection, entry_id).await?;
+    // the entry could have been deleted between the check and the lock
+    let Some(old_entry) = old_entry_option.filter(|entry| entry.deleted.is_none()) else {
+        return Err(EntryOperationError::NotFound("entry not found"));
+    };
+    check_entry_precondition(&old_entry, if_match)?;
+    check_entry_not_archived(&old_entry)?;
+    let sets_purchase = matches!(payload.price, Some(Some(_)))
+        || matches!(payload.currency, Some(Some(_)))
+        || matches!(payload.store_id, Some(Some(_)));
+    if sets_purchase && !payload.bought.unwrap_or(old_entry.bought.is_some()) {
+        return Err(EntryOperationError::BadRequest(
+            "only bought entries can have a price, currency or store",
+        ));
+    }
+    if let Some(Some(price)) = payload.price {
+        if price.is_sign_negative() {
+            return Err(EntryOperationError::BadRequest(
+                "price must not be negative",
+            ));
+        }
+    }
+    if let Some(Some(currency)) = &payload.currency {
+        if !is_valid_currency(currency) {
+            return Err(EntryOperationError::BadRequest(
+                "currency must be a three letter code like \"EUR\"",
+            ));
+        }
+    }
+    if let Some(Some(store_id)) = payload.store_id {
+        if !is_store_of_list_owner(&mut *connection, store_id, old_entry.list_id).await? {
+            return Err(EntryOperationError::BadRequest(
+                "the store has to belong to the owner of the list of the entry",
+            ));
+        }
+    }
+    // entries that are not bought anymore lose their price, currency and store
+    let is_unbought = payload.bought == Some(false);
+
+    let mut query_builder = QueryBuilder::<Postgres>::new("update entries set ");
+    // the assignments have to be separated by commas
+    let mut assignments = query_builder.separated(", ");
+    if let Some(product) = payload.product {
+        assignments.push("product = ");
+        assignments.push_bind_unseparated(product);
+    }
+    if let Some(value) = payload.amount {
+        assignments.push("amount = ");
+        assignments.push_bind_unseparated(value);
+    }
+    if let Some(value) = payload.unit {
+        assignments.push("unit = ");
+        assignments.push_bind_unseparated(value);
+    }
+    if let Some(value) = payload.note {
+        assignments.push("note = ");
+        assignments.push_bind_unseparated(value);
+    }
+    if let Some(value) = payload.bought {
+        // the buyer is always the authenticated user and is removed together with the time
+        // if the buyer is on a trip for the list of the entry, the entry becomes part of the trip
+        if value {
+            assignments.push("bought = now()");
+            assignments.push("bought_by = ");
+            assignments.push_bind_unseparated(user_id);
+            assignments.push(
+                "trip_id = (select id from trips where ended is null and list_id = entries.list_id and shopper_id = ",
+            );
+            assignments.push_bind_unseparated(user_id);
+            assignments.push_unseparated(")");
+            // it has been bought at the store of the trip, unless another store has been given
+            if payload.store_id.is_none() {
+                assignments.push(
+                    "store_id = (select store_id from trips where ended is null and list_id = entries.list_id and shopper_id = ",
+                );
+                assignments.push_bind_unseparated(user_id);
+                assignments.push_unseparated(")");
+            }
+        } else {
+            assignments.push("bought = null");
+            assignments.push("bought_by = null");
+            assignments.push("trip_id = null");
+            assignments.push("price = null");
+            assignments.push("currency = null");
+            assignments.push("store_id = null");
+        }
+    }
+    // new values for entries that are not bought anymore have already been rejected above,
+    // so only nulls would be assigned a second time here
+    if !is_unbought {
+        if let Some(value) = payload.price {
+            assignments.push("price = ");
+            assignments.push_bind_unseparated(value);
+        }
+        if let Some(value) = payload.currency {
+            assignments.push("currency = ");
+            assignments.push_bind_unseparated(value.map(|currency| currency.to_ascii_uppercase()));
+        }
+        if let Some(value) = payload.store_id {
+            assignments.push("store_id = ");
+            assignments.push_bind_unseparated(value);
+        }
+    }
+
+    query_builder.push(" where id = ");
+    query_builder.push_bind(entry_id);
+    query_builder.push(
+        " returning id, product, amount, unit, note, user_id, list_id,
+        (select group_id from lists where lists.id = entries.list_id) as group_id,
+        created, bought, bought_by, deleted, deleted_by, archive_id, trip_id, price, currency, store_id, version",
+    );
+
+    let entry_option = query_builder
+        .build_query_as::<Entry>()
+        .fetch_optional(&mut *connection)
+        .await?;
+    let Some(entry) = entry_option else {
+        return Err(EntryOperationError::NotFound("entry not found"));
+    };
+    let kind = match (old_entry.bought, entry.bought) {
+        (None, Some(_)) => EntryEventKind::Bought,
+        (Some(_), None) => EntryEventKind::Unbought,
+        _ => EntryEventKind::Updated,
+    };
+    record_entry_event(
+        &mut *connection,
+        user_id,
+        kind,
+        Some(&old_entry),
+        Some(&entry),
+    )
+    .await?;
+    Ok(entry)
+}
+
+pub(super) async fn patch_entry(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    entry_id: web::Path<i64>,
+    user_id: ReqData<i64>,
+    payload: Json<PatchEntryRequestData>,
+) -> HttpResponse {
+    let pool = &app_data.pool;
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let entry_result = update_entry(
+        &mut transaction,
+        user_id.into_inner(),
+        entry_id.into_inner(),
+        payload.into_inner(),
+        request.get_header::<header::IfMatch>().as_ref(),
+    )
+    .await;
+    let entry = match entry_result {
+        Ok(entry) => entry,
+        Err(err) => return err.into_entry_response(&request),
+    };
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(entry.rest_resource(&request));
+    HttpResponse::Ok()
+        .insert_header(header::ETag(entry.etag()))
+        .json(rest_resource)
+}
+
+// entries are only moved to the trash, they are purged after the retention period
+// returns the entry as it was before it has been deleted
+async fn trash_entry(
+    connection: &mut PgConnection,
+    user_id: i64,
+    entry_id: i64,
+    if_match: Option<&header::IfMatch>,
+) -> Result<Entry, EntryOperationError> {
+    if !can_modify_entry(&mut *connection, user_id, entry_id).await? {
+        return Err(EntryOperationError::NotFound("entry not found"));
+    }
+
+    let entry_option = fetch_entry_for_update(&mut *connection, entry_id).await?;
+    let Some(entry) = entry_option.filter(|entry| entry.deleted.is_none()) else {
+        return Err(EntryOperationError::NotFound("entry not found"));
+    };
+    check_entry_precondition(&entry, if_match)?;
+    check_entry_not_archived(&entry)?;
+    sqlx::query!(
+        "update entries set deleted = now(), deleted_by = $1 where id = $2",
+        user_id,
+        entry_id,
+    )
+    .execute(&mut *connection)
+    .await?;
+    record_entry_event(
+        &mut *connection,
+        user_id,
+        EntryEventKind::Deleted,
+        Some(&entry),
+        None,
+    )
+    .await?;
+    Ok(entry)
+}
+
+pub(super) async fn delete_entry(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    entry_id: web::Path<i64>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let pool = &app_data.pool;
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let entry_result = trash_entry(
+        &mut transaction,
+        user_id.into_inner(),
+        entry_id.into_inner(),
+        request.get_header::<header::IfMatch>().as_ref(),
+    )
+    .await;
+    if let Err(err) = entry_result {
+        return err.into_entry_response(&request);
+    }
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    HttpResponse::NoContent().finish()
+}
+
+const BATCH_MAX_OPERATIONS: usize = 100;
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub(super) enum BatchOperation {
+    Create {
+        entry: PostEntryRequestData,
+    },
+    // the version works like the if-match header of the single endpoint,
+    // the operation fails if the entry has been changed since
+    Patch {
+        id: i64,
+        changes: PatchEntryRequestData,
+        version: Option<i64>,
+    },
+    Delete {
+        id: i64,
+        version: Option<i64>,
+    },
+}
+
+fn default_atomic() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub(super) struct BatchRequestData {
+    // either all operations are applied or none of them,
+    // otherwise every operation is applied on its own and the results show which ones failed
+    #[serde(default = "default_atomic")]
+    atomic: bool,
+    operations: Vec<BatchOperation>,
+}
+
+#[derive(Serialize)]
+struct BatchOperationResult<'a> {
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entry: Option<RestResource<'a, Entry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'static str>,
+}
+
+fn version_if_match(version: Option<i64>) -> Option<header::IfMatch> {
+    version.map(|version| header::IfMatch::Items(vec![Entry::version_etag(version)]))
+}
+
+// returns the status the single endpoint would respond with
+// and the entry, unless it has been deleted
+async fn apply_batch_operation(
+    connection: &mut PgConnection,
+    user_id: i64,
+    operation: BatchOperation,
+) -> Result<(StatusCode, Option<Entry>), EntryOperationError> {
+    match operation {
+        BatchOperation::Create { entry } => {
+            let entry = create_entry(connection, user_id, entry, None).await?;
+            Ok((StatusCode::CREATED, Some(entry)))
+        }
+        BatchOperation::Patch {
+            id,
+            changes,
+            version,
+        } => {
+            let if_match = version_if_match(version);
+            let entry = update_entry(connection, user_id, id, changes, if_match.as_ref()).await?;
+            Ok((StatusCode::OK, Some(entry)))
+        }
+        BatchOperation::Delete { id, version } => {
+            let if_match = version_if_match(version);
+            trash_entry(connection, user_id, id, if_match.as_ref()).await?;
+            Ok((StatusCode::NO_CONTENT, None))
+        }
+    }
+}
+
+// applies several operations in one transaction,
+// each of them is checked like it would be by its single endpoint
+pub(super) async fn post_entries_batch(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<BatchRequestData>,
+) -> HttpResponse {
+    let payload = payload.into_inner();
+    if payload.operations.is_empty() || payload.operations.len() > BATCH_MAX_OPERATIONS {
+        return HttpResponse::BadRequest().json(format!(
+            "operations must contain between 1 and {BATCH_MAX_OPERATIONS} operations"
+        ));
+    }
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let mut outcomes = Vec::with_capacity(payload.operations.len());
+    for (index, operation) in payload.operations.into_iter().enumerate() {
+        let outcome = if payload.atomic {
+            apply_batch_operation(&mut transaction, user_id, operation).await
+        } else {
+            // every operation gets its own savepoint,
+            // so a failed operation doesn't undo the others
+            let mut savepoint =
+                ok_or_log_and_respond_internal_server_error!(transaction.begin().await);
+            let outcome = apply_batch_operation(&mut savepoint, user_id, operation).await;
+            if outcome.is_ok() {
+                ok_or_log_and_respond_internal_server_error!(savepoint.commit().await);
+            }
+            outcome
+        };
+        match outcome {
+            // errors of the database fail the whole batch, no matter if it is atomic
+            Err(err @ EntryOperationError::Database(_)) => return err.into_response(),
+            // the transaction is rolled back when it is dropped
+            Err(err) if payload.atomic => {
+                let mut body = json!({ "index": index, "error": err.message() });
+                if let Some(entry) = err.current_entry() {
+                    let rest_resource =
+                        ok_or_log_and_respond_internal_server_error!(entry.rest_resource(&request));
+                    body["entry"] = json!(rest_resource);
+                }
+                return HttpResponseBuilder::new(err.status()).json(body);
+            }
+            outcome => outcomes.push(outcome),
+        }
+    }
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    let mut results = Vec::with_capacity(outcomes.len());
+    for outcome in &outcomes {
+        let result = match outcome {
+            Ok((status, entry_option)) => BatchOperationResult {
+                status: status.as_u16(),
+                entry: match entry_option {
+                    Some(entry) => Some(ok_or_log_and_respond_internal_server_error!(
+                        entry.rest_resource(&request)
+                    )),
+                    None => None,
+                },
+                error: None,
+            },
+            Err(err) => BatchOperationResult {
+                status: err.status().as_u16(),
+                entry: match err.current_entry() {
+                    Some(entry) => Some(ok_or_log_and_respond_internal_server_error!(
+                        entry.rest_resource(&request)
+                    )),
+                    None => None,
+                },
+                error: Some(err.message()),
+            },
+        };
+        results.push(result);
+    }
+
+    // the status of every single operation is part of the results
+    HttpResponse::Ok().json(results)
+}
+
+pub(super) async fn restore_entry(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    entry_id: web::Path<i64>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let entry_id = entry_id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let can_restore_entry = ok_or_log_and_respond_internal_server_error!(
+        can_restore_entry(pool, user_id, entry_id).await
+    );
+    if !can_restore_entry {
+        return HttpResponse::NotFound().json("entry not found in the trash");
+    }
+
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let entry_option = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Entry,
+            r#"update entries set deleted = null, deleted_by = null
+            where id = $1 and deleted is not null
+            returning id, product, amount, unit, note, user_id, list_id,
+                (select group_id from lists where lists.id = entries.list_id) as group_id,
+                created, bought, bought_by, deleted, deleted_by, archive_id, trip_id, price, currency, store_id, version"#,
+            entry_id,
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+    );
+    // the entry could have been restored or purged between the check and the update
+    let Some(entry) = entry_option else {
+        return HttpResponse::NotFound().json("entry not found in the trash");
+    };
+    ok_or_log_and_respond_internal_server_error!(
+        record_entry_event(
+            &mut *transaction,
+            user_id,
+            EntryEventKind::Restored,
+            None,
+            Some(&entry)
+        )
+        .await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(entry.rest_resource(&request));
+
+    HttpResponse::Ok()
+        .insert_header(header::ETag(entry.etag()))
+        .json(rest_resource)
+}
+
+pub(super) async fn get_entry_by_id(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    entry_id: web::Path<i64>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let entry_id = entry_id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let can_read_entry =
+        ok_or_log_and_respond_internal_server_error!(can_read_entry(pool, user_id, entry_id).await);
+    if !can_read_entry {
+        return HttpResponse::NotFound().json("entry not found");
+    }
+
+    let row_result = sqlx::query!(
+        r#"select
+            e.id, e.product, e.amount, e.unit, e.note, e.created, e.bought, e.bought_by, e.deleted, e.deleted_by, e.archive_id, e.trip_id, e.price, e.currency, e.store_id, e.version, e.user_id, e.list_id, l.group_id,
+            coalesce(e.updated, e.created) as "last_modified!"
+            from
+                entries as e
+            inner join
+                lists as l
+                    on l.id = e.list_id
+            left outer join
+                users_groups_relations as ugr
+                    on ugr.group_id = l.group_id
+                    and ugr.user_id = $1
+            where
+                (l.user_id = $1
+                or ugr.group_id is not null)
+                and e.id = $2
+                and e.deleted is null
+            order by e.id"#,
+        user_id,
+        entry_id,
+    )
+    .fetch_optional(pool)
+    .await;
+    let row_option = ok_or_log_and_respond_internal_server_error!(row_result);
+    let row = match row_option {
+        Some(value) => value,
+        None => return HttpResponse::NotFound().json("entry not found"),
+    };
+    let entry = Entry {
+        id: row.id,
+        product: row.product,
+        amount: row.amount,
+        unit: row.unit,
+        note: row.note,
+        created: row.created,
+        bought: row.bought,
+        bought_by: row.bought_by,
+        deleted: row.deleted,
+        deleted_by: row.deleted_by,
+        archive_id: row.archive_id,
+        trip_id: row.trip_id,
+        price: row.price,
+        currency: row.currency,
+        store_id: row.store_id,
+        version: row.version,
+        user_id: row.user_id,
+        list_id: row.list_id,
+        group_id: row.group_id,
+    };
+
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(entry.rest_resource(&request));
+
+    respond_with_validators(
+        &request,
+        HttpResponse::Ok(),
+        &rest_resource,
+        Some(entry.etag()),
+        Some(row.last_modified),
+    )
+}
+
+pub(super) async fn get_entry_history(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    entry_id: web::Path<i64>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let entry_id = entry_id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+
+    // the access is checked with the list of the events instead of the entry,
+    // so the history of deleted entries can still be read
+    let events = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            EntryEvent,
+            r#"select
+                ev.id, ev.entry_id, ev.list_id, l.group_id, ev.actor_id,
+                ev.kind as "kind: EntryEventKind", ev.changes, ev.created
+            from
+                entry_events as ev
+            inner join
+                lists as l
+                    on l.id = ev.list_id
+            left outer join
+                users_groups_relations as ugr
+                    on ugr.group_id = l.group_id
+                    and ugr.user_id = $1
+            where
+                ev.entry_id = $2
+                and (l.user_id = $1 or ugr.group_id is not null)
+            order by ev.id"#,
+            user_id,
+            entry_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    // entries that were inserted directly into the database have no events
+    if events.is_empty() {
+        let can_read_entry = ok_or_log_and_respond_internal_server_error!(
+            can_read_entry(pool, user_id, entry_id).await
+        );
+        if !can_read_entry {
+            return HttpResponse::NotFound().json("entry not found");
+        }
+    }
+
+    let body = all_ok_or_log_and_respond_internal_server_error!(events
+        .iter()
+        .map(|event| event.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+// idle event streams get a comment this often, so proxies don't close them
+// and disconnected clients are noticed
+const EVENT_STREAM_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// formats a message of a server-sent event stream
+fn server_sent_event(event: &str, data: &impl Serialize) -> Result<web::Bytes, serde_json::Error> {
+    let data = serde_json::to_string(data)?;
+    Ok(web::Bytes::from(format!(
+        "event: {event}\ndata: {data}\n\n"
+    )))
+}
+
+// returns the entry if the user can see it, even if it has been deleted or archived
+async fn fetch_visible_entry(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    entry_id: i64,
+) -> Result<Option<Entry>, sqlx::Error> {
+    sqlx::query_as!(
+        Entry,
+        r#"select e.id, e.product, e.amount, e.unit, e.note, e.created, e.bought, e.bought_by, e.deleted, e.deleted_by, e.archive_id, e.trip_id, e.price, e.currency, e.store_id, e.version, e.user_id, e.list_id, l.group_id
+        from entries as e
+        inner join lists as l on l.id = e.list_id
+        left outer join users_groups_relations as ugr
+            on ugr.group_id = l.group_id
+            and ugr.user_id = $1
+        where e.id = $2 and (l.user_id = $1 or ugr.group_id is not null)"#,
+        user_id,
+        entry_id,
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// turns a published change into an event for the user, if they can see the entry
+// purged entries can't be fetched anymore, so only their id is sent
+async fn entry_change_event(
+    request: &actix_web::HttpRequest,
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    change: EntryChange,
+) -> Option<web::Bytes> {
+    if change.kind == EntryEventKind::Purged {
+        return server_sent_event("purged", &json!({ "id": change.entry_id }))
+            .inspect_err(|err| log::error!("Failed to serialize event: {}", err))
+            .ok();
+    }
+    let entry_option = fetch_visible_entry(pool, user_id, change.entry_id)
+        .await
+        .inspect_err(|err| log::error!("Failed to fetch entry for event stream: {}", err))
+        .ok()?;
+    // the entry has been purged since or the user can't see it
+    let entry = entry_option?;
+    let rest_resource = entry.rest_resource(request).ok()?;
+    let kind = serde_json::to_value(change.kind).ok()?;
+    server_sent_event(kind.as_str()?, &rest_resource)
+        .inspect_err(|err| log::error!("Failed to serialize event: {}", err))
+        .ok()
+}
+
+// what the request of a long running connection has been authenticated with,
+// so the connection can be closed once the session or the api key is revoked or expires
+#[derive(Clone, Copy, Debug)]
+enum Credentials {
+    // the password is not kept, so only the user itself can go away
+    Password,
+    Session(i64),
+    ApiKey(i64),
+}
+
+impl Credentials {
+    fn of_request(
+        session_id: Option<ReqData<SessionId>>,
+        api_key_scope: Option<ReqData<ApiKeyScope>>,
+    ) -> Self {
+        match (session_id, api_key_scope) {
+            (Some(session_id), _) => Credentials::Session(session_id.0),
+            (None, Some(api_key_scope)) => Credentials::ApiKey(api_key_scope.id),
+            (None, None) => Credentials::Password,
+        }
+    }
+
+    async fn are_valid(
+        self,
+        executor: impl PgExecutor<'_>,
+        user_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let is_valid = match self {
+            Credentials::Password => {
+                sqlx::query_scalar!(
+                    r#"select exists (select 1 from users where id = $1) as "exists!""#,
+                    user_id,
+                )
+                .fetch_one(executor)
+                .await?
+            }
+            Credentials::Session(session_id) => {
+                sqlx::query_scalar!(
+                    r#"select exists (select 1 from sessions where id = $1 and expires > now()) as "exists!""#,
+                    session_id,
+                )
+                .fetch_one(executor)
+                .await?
+            }
+            Credentials::ApiKey(api_key_id) => {
+                sqlx::query_scalar!(
+                    r#"select exists (select 1 from api_keys where id = $1 and (expires is null or expires > now())) as "exists!""#,
+                    api_key_id,
+                )
+                .fetch_one(executor)
+                .await?
+            }
+        };
+        Ok(is_valid)
+    }
+}
+
+async fn fetch_group_ids(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+) -> Result<HashSet<i64>, sqlx::Error> {
+    let group_ids = sqlx::query_scalar!(
+        "select group_id from users_groups_relations where user_id = $1",
+        user_id,
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(group_ids.into_iter().collect())
+}
+
+// the state of an event stream between its events
+struct EntryEventStream {
+    request: actix_web::HttpRequest,
+    pool: Pool<Postgres>,
+    user_id: i64,
+    credentials: Credentials,
+    // only the changes of this group are streamed, if it is set
+    group_id: Option<i64>,
+    // the groups of the user, so changes of other groups are skipped without querying the entry
+    group_ids: HashSet<i64>,
+    receiver: broadcast::Receiver<LiveUpdate>,
+    keep_alive: actix_web::rt::time::Interval,
+}
+
+impl EntryEventStream {
+    fn concerns_user(&self, change: &EntryChange) -> bool {
+        match change.group_id {
+            Some(group_id) => {
+                self.group_ids.contains(&group_id) && self.group_id.is_none_or(|id| id == group_id)
+            }
+            None => self.group_id.is_none() && change.user_id == Some(self.user_id),
+        }
+    }
+
+    // returns None once the stream has to end
+    async fn next_event(&mut self) -> Option<web::Bytes> {
+        loop {
+            tokio::select! {
+                update = self.receiver.recv() => match update {
+                    Ok(LiveUpdate::Entry(change)) => {
+                        if !self.concerns_user(&change) {
+                            continue;
+                        }
+                        let event =
+                            entry_change_event(&self.request, &self.pool, self.user_id, change).await;
+                        if let Some(event) = event {
+                            return Some(event);
+                        }
+                    }
+                    Ok(LiveUpdate::Access { group_id, user_id }) => {
+                        let concerns_user = user_id.is_none_or(|user_id| user_id == self.user_id)
+                            && (user_id.is_some() || self.group_ids.contains(&group_id));
+                        if !concerns_user {
+                            continue;
+                        }
+                        match fetch_group_ids(&self.pool, self.user_id).await {
+                            Ok(group_ids) => self.group_ids = group_ids,
+                            Err(err) => log::error!("Failed to fetch groups for event stream: {}", err),
+                        }
+                    }
+                    // presence is only shared in the channels of the lists
+                    Ok(LiveUpdate::Presence { .. }) => {}
+                    // the client should fetch the entries again, it can't know what it missed
+                    Err(RecvError::Lagged(missed)) => {
+                        if let Ok(event) = server_sent_event("lagged", &json!({ "missed": missed })) {
+                            return Some(event);
+                        }
+                    }
+                    // how many changes were missed is unknown
+                    Ok(LiveUpdate::Lagged) => {
+                        if let Ok(event) = server_sent_event("lagged", &json!({ "missed": null })) {
+                            return Some(event);
+                        }
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = self.keep_alive.tick() => {
+                    match self.credentials.are_valid(&self.pool, self.user_id).await {
+                        Ok(true) => {}
+                        Ok(false) => return None,
+                        Err(err) => log::error!("Failed to check credentials of event stream: {}", err),
+                    }
+                    return Some(web::Bytes::from_static(b": keep-alive\n\n"));
+                }
+            }
+        }
+    }
+}
+
+// streams the changes of the entries the user can see, optionally only those of one group
+// the event is the kind of the change and the data is the entry as it is now,
+// or only its id once it has been purged
+// the stream ends at the next keep-alive, once its credentials are not valid anymore
+async fn respond_with_entry_events(
+    request: actix_web::HttpRequest,
+    app_data: &AppData,
+    user_id: i64,
+    credentials: Credentials,
+    group_id: Option<i64>,
+) -> HttpResponse {
+    // subscribing before fetching the groups, so no change of access is missed in between
+    let receiver = app_data.live_updates.subscribe();
+    let group_ids = ok_or_log_and_respond_internal_server_error!(
+        fetch_group_ids(&app_data.pool, user_id).await
+    );
+    let state = EntryEventStream {
+        request,
+        pool: app_data.pool.clone(),
+        user_id,
+        credentials,
+        group_id,
+        group_ids,
+        receiver,
+        keep_alive: actix_web::rt::time::interval(EVENT_STREAM_KEEP_ALIVE_INTERVAL),
+    };
+    let events = stream::unfold(state, |mut state| async move {
+        let event = state.next_event().await?;
+        Some((Ok::<_, actix_web::Error>(event), state))
+    });
+
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        .streaming(events)
+}
+
+pub(super) async fn get_entry_events(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    session_id: Option<ReqData<SessionId>>,
+    api_key_scope: Option<ReqData<ApiKeyScope>>,
+) -> HttpResponse {
+    respond_with_entry_events(
+        request,
+        &app_data,
+        user_id.into_inner(),
+        Credentials::of_request(session_id, api_key_scope),
+        None,
+    )
+    .await
+}
+
+pub(super) async fn get_group_entry_events(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    session_id: Option<ReqData<SessionId>>,
+    api_key_scope: Option<ReqData<ApiKeyScope>>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let is_member = ok_or_log_and_respond_internal_server_error!(
+        is_member(&app_data.pool, user_id, group_id).await
+    );
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    respond_with_entry_events(
+        request,
+        &app_data,
+        user_id,
+        Credentials::of_request(session_id, api_key_scope),
+        Some(group_id),
+    )
+    .await
+}
+
+// the server pings the client this often and checks if it may still access the list
+const LIST_CHANNEL_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+// the connection is closed if the client hasn't sent anything for this long
+const LIST_CHANNEL_CLIENT_TIMEOUT: Duration = Duration::from_secs(45);
+
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum ListChannelAction {
+    Create {
+        entry: PostEntryRequestData,
+    },
+    // the version is checked like by the operations of a batch
+    Patch {
+        id: i64,
+        changes: PatchEntryRequestData,
+        version: Option<i64>,
+    },
+    Delete {
+        id: i64,
+        version: Option<i64>,
+    },
+    Presence {
+        status: PresenceStatus,
+    },
+}
+
+#[derive(Deserialize)]
+struct ListChannelCommand {
+    // chosen by the client and sent back with the ack, so it can tell which command it belongs to
+    #[serde(default)]
+    request_id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    action: ListChannelAction,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ListChannelMessage<'a> {
+    // the result of a command, with the status the rest endpoint would respond with
+    Ack {
+        request_id: Option<serde_json::Value>,
+        #[serde(flatten)]
+        result: BatchOperationResult<'a>,
+    },
+    // a change of an entry of the list, made by anyone, including the receiver
+    Entry {
+        kind: EntryEventKind,
+        entry: RestResource<'a, Entry>,
+    },
+    // an entry of the list has been deleted permanently, so only its id is left
+    Purged {
+        id: i64,
+    },
+    Presence {
+        users: Vec<Presence>,
+    },
+    // the client should fetch the entries again, it can't know what it missed
+    // null if not even the number of missed changes is known
+    Lagged {
+        missed: Option<u64>,
+    },
+    // the message of the client was not a valid command
+    Error {
+        error: String,
+    },
+}
+
+async fn send_list_channel_message(
+    session: &mut actix_ws::Session,
+    message: &ListChannelMessage<'_>,
+) -> Result<(), actix_ws::Closed> {
+    match serde_json::to_string(message) {
+        Ok(text) => session.text(text).await,
+        Err(err) => {
+            log::error!("Failed to serialize list channel message: {}", err);
+            Ok(())
+        }
+    }
+}
+
+// entries can only be changed through the channel of their own list
+async fn is_entry_on_list(
+    executor: impl PgExecutor<'_>,
+    entry_id: i64,
+    list_id: i64,
+) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query!(
+        r#"select exists (select 1 from entries where id = $1 and list_id = $2) as "exists!: bool""#,
+        entry_id,
+        list_id,
+    )
+    .fetch_one(executor)
+    .await?
+    .exists)
+}
+
+// applies an operation like the batch endpoint does,
+// the change reaches the channels through the notification of the database
+async fn apply_list_channel_operation(
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    list_id: i64,
+    operation: BatchOperation,
+) -> Result<(StatusCode, Option<Entry>), EntryOperationError> {
+    let mut transaction = pool.begin().await?;
+    match &operation {
+        BatchOperation::Create { .. } => {}
+        BatchOperation::Patch { id, .. } | BatchOperation::Delete { id, .. } => {
+            if !is_entry_on_list(&mut *transaction, *id, list_id).await? {
+                return Err(EntryOperationError::NotFound("entry not found"));
+            }
+        }
+    }
+    let outcome = apply_batch_operation(&mut transaction, user_id, operation).await?;
+    transaction.commit().await?;
+    Ok(outcome)
+}
+
+// returns the id of the connection, which is needed to change its status and to leave
+async fn join_list_channel(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    list_id: i64,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        "insert into list_presences (list_id, user_id) values ($1, $2) returning id",
+        list_id,
+        user_id,
+    )
+    .fetch_one(executor)
+    .await
+}
+
+async fn set_list_channel_status(
+    executor: impl PgExecutor<'_>,
+    connection_id: i64,
+    status: PresenceStatus,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "update list_presences set status = $1, seen = now() where id = $2",
+        status as PresenceStatus,
+        connection_id,
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+// keeps the connection from being removed and removes the connections of the list
+// that the instances of the server which stopped left behind
+async fn refresh_list_channel_presence(
+    pool: &Pool<Postgres>,
+    list_id: i64,
+    connection_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "update list_presences set seen = now() where id = $1",
+        connection_id,
+    )
+    .execute(pool)
+    .await?;
+    sqlx::query!(
+        "delete from list_presences where list_id = $1 and seen < now() - $2::interval",
+        list_id,
+        LIST_CHANNEL_CLIENT_TIMEOUT as _,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn leave_list_channel(
+    executor: impl PgExecutor<'_>,
+    connection_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("delete from list_presences where id = $1", connection_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+// every user is only listed once, with the most active status of their connections
+async fn fetch_presences(
+    executor: impl PgExecutor<'_>,
+    list_id: i64,
+) -> Result<Vec<Presence>, sqlx::Error> {
+    sqlx::query_as!(
+        Presence,
+        r#"select u.id as user_id, u.display_name, max(p.status) as "status!: PresenceStatus"
+        from list_presences as p
+        inner join users as u on u.id = p.user_id
+        where p.list_id = $1 and p.seen >= now() - $2::interval
+        group by u.id
+        order by u.id"#,
+        list_id,
+        LIST_CHANNEL_CLIENT_TIMEOUT as _,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+// sends the result of a command, with the status the rest endpoint would respond with
+async fn send_list_channel_ack(
+    request: &actix_web::HttpRequest,
+    session: &mut actix_ws::Session,
+    request_id: Option<serde_json::Value>,
+    outcome: Result<(StatusCode, Option<Entry>), EntryOperationError>,
+) -> Result<(), actix_ws::Closed> {
+    let result = match &outcome {
+        Ok((status, entry_option)) => {
+            let entry = match entry_option {
+                Some(entry) => entry.rest_resource(request).ok(),
+                None => None,
+            };
+            BatchOperationResult {
+                status: status.as_u16(),
+                entry,
+                error: None,
+            }
+        }
+        Err(err) => BatchOperationResult {
+            status: err.status().as_u16(),
+            entry: err
+                .current_entry()
+                .and_then(|entry| entry.rest_resource(request).ok()),
+            error: Some(err.message()),
+        },
+    };
+    let ack = ListChannelMessage::Ack { request_id, result };
+    send_list_channel_message(session, &ack).await
+}
+
+// the user that opened the channel of a list
+struct ListChannelUser {
+    id: i64,
+    credentials: Credentials,
+    // read only api keys can only set their presence, the channel is opened with a get request
+    // so the scope of the api key doesn't prevent writing through it
+    read_only: bool,
+}
+
+async fn handle_list_channel_command(
+    request: &actix_web::HttpRequest,
+    app_data: &AppData,
+    session: &mut actix_ws::Session,
+    user: &ListChannelUser,
+    list_id: i64,
+    connection_id: i64,
+    text: &str,
+) -> Result<(), actix_ws::Closed> {
+    let command = match serde_json::from_str::<ListChannelCommand>(text) {
+        Ok(command) => command,
+        Err(err) => {
+            let error = ListChannelMessage::Error {
+                error: err.to_string(),
+            };
+            return send_list_channel_message(session, &error).await;
+        }
+    };
+    let operation = match command.action {
+        ListChannelAction::Presence { status } => {
+            let outcome = set_list_channel_status(&app_data.pool, connection_id, status)
+                .await
+                .map(|()| (StatusCode::OK, None))
+                .map_err(EntryOperationError::from);
+            return send_list_channel_ack(request, session, command.request_id, outcome).await;
+        }
+        _ if user.read_only => Err(EntryOperationError::Forbidden(
+            "the api key can only be used for reading",
+        )),
+        ListChannelAction::Create { mut entry } => {
+            if entry
+                .list_id
+                .is_some_and(|entry_list_id| entry_list_id != list_id)
+            {
+                Err(EntryOperationError::BadRequest(
+                    "list_id does not match the list of the channel",
+                ))
+            } else {
+                entry.list_id = Some(list_id);
+                Ok(BatchOperation::Create { entry })
+            }
+        }
+        ListChannelAction::Patch {
+            id,
+            changes,
+            version,
+        } => Ok(BatchOperation::Patch {
+            id,
+            changes,
+            version,
+        }),
+        ListChannelAction::Delete { id, version } => Ok(BatchOperation::Delete { id, version }),
+    };
+    let outcome = match operation {
+        Ok(operation) => {
+            apply_list_channel_operation(&app_data.pool, user.id, list_id, operation).await
+        }
+        Err(err) => Err(err),
+    };
+    send_list_channel_ack(request, session, command.request_id, outcome).await
+}
+
+// turns an update of the hub into a message for the channel, if it concerns its list
+async fn send_list_channel_update(
+    request: &actix_web::HttpRequest,
+    app_data: &AppData,
+    session: &mut actix_ws::Session,
+    user_id: i64,
+    list_id: i64,
+    update: Result<LiveUpdate, RecvError>,
+) -> Result<(), actix_ws::Closed> {
+    match update {
+        Ok(LiveUpdate::Entry(change))
+            if change.list_id == list_id && change.kind == EntryEventKind::Purged =>
+        {
+            let message = ListChannelMessage::Purged {
+                id: change.entry_id,
+            };
+            send_list_channel_message(session, &message).await
+        }
+        Ok(LiveUpdate::Entry(change)) if change.list_id == list_id => {
+            let entry_option =
+                match fetch_visible_entry(&app_data.pool, user_id, change.entry_id).await {
+                    Ok(entry_option) => entry_option,
+                    Err(err) => {
+                        log::error!("Failed to fetch entry for list channel: {}", err);
+                        return Ok(());
+                    }
+                };
+            // the entry has been purged since or the user can't see it anymore
+            let Some(entry) = entry_option else {
+                return Ok(());
+            };
+            let Ok(rest_resource) = entry.rest_resource(request) else {
+                return Ok(());
+            };
+            let message = ListChannelMessage::Entry {
+                kind: change.kind,
+                entry: rest_resource,
+            };
+            send_list_channel_message(session, &message).await
+        }
+        Ok(LiveUpdate::Presence {
+            list_id: changed_list_id,
+        }) if changed_list_id == list_id => {
+            let users = match fetch_presences(&app_data.pool, list_id).await {
+                Ok(users) => users,
+                Err(err) => {
+                    log::error!("Failed to fetch presences for list channel: {}", err);
+                    return Ok(());
+                }
+            };
+            let message = ListChannelMessage::Presence { users };
+            send_list_channel_message(session, &message).await
+        }
+        Ok(LiveUpdate::Lagged) => {
+            send_list_channel_message(session, &ListChannelMessage::Lagged { missed: None }).await
+        }
+        Ok(_) => Ok(()),
+        Err(RecvError::Lagged(missed)) => {
+            let message = ListChannelMessage::Lagged {
+                missed: Some(missed),
+            };
+            send_list_channel_message(session, &message).await
+        }
+        Err(RecvError::Closed) => Err(actix_ws::Closed),
+    }
+}
+
+// returns why the channel has to be closed, if the user can't access the list anymore
+async fn lost_list_channel_access(
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    list_id: i64,
+) -> Option<actix_ws::CloseReason> {
+    match fetch_list(pool, user_id, list_id).await {
+        Ok(Some(_)) => None,
+        Ok(None) => Some(actix_ws::CloseReason {
+            code: actix_ws::CloseCode::Policy,
+            description: Some("list not found".to_string()),
+        }),
+        Err(err) => {
+            log::error!("Failed to check access to list channel: {}", err);
+            None
+        }
+    }
+}
+
+// runs until the client disconnects, loses access to the list or stops responding
+// or its session or api key is revoked or expires
+async fn run_list_channel(
+    request: actix_web::HttpRequest,
+    app_data: web::Data<AppData>,
+    user: ListChannelUser,
+    list: List,
+    mut session: actix_ws::Session,
+    mut messages: actix_ws::MessageStream,
+) {
+    let user_id = user.id;
+    let list_id = list.id;
+    // subscribing before joining, so the client gets the presence including itself
+    let mut receiver = app_data.live_updates.subscribe();
+    let connection_id = match join_list_channel(&app_data.pool, user_id, list_id).await {
+        Ok(connection_id) => connection_id,
+        Err(err) => {
+            log::error!("Failed to join list channel: {}", err);
+            let close_reason = actix_ws::CloseReason {
+                code: actix_ws::CloseCode::Error,
+                description: Some("internal server error".to_string()),
+            };
+            let _ = session.close(Some(close_reason)).await;
+            return;
+        }
+    };
+    let mut heartbeat = actix_web::rt::time::interval(LIST_CHANNEL_HEARTBEAT_INTERVAL);
+    let mut last_message = Instant::now();
+
+    let close_reason = loop {
+        tokio::select! {
+            message = messages.recv() => {
+                let Some(Ok(message)) = message else {
+                    break None;
+                };
+                last_message = Instant::now();
+                let result = match message {
+                    actix_ws::Message::Text(text) => {
+                        handle_list_channel_command(
+                            &request,
+                            &app_data,
+                            &mut session,
+                            &user,
+                            list_id,
+                            connection_id,
+                            &text,
+                        )
+                        .await
+                    }
+                    actix_ws::Message::Ping(bytes) => session.pong(&bytes).await,
+                    actix_ws::Message::Close(reason) => break reason,
+                    _ => Ok(()),
+                };
+                if result.is_err() {
+                    break None;
+                }
+            }
+            update = receiver.recv() => {
+                if let Ok(LiveUpdate::Access { group_id, user_id: changed_user_id }) = update {
+                    let concerns_channel = list.group_id == Some(group_id)
+                        && changed_user_id.is_none_or(|changed_user_id| changed_user_id == user_id);
+                    if concerns_channel {
+                        if let Some(reason) =
+                            lost_list_channel_access(&app_data.pool, user_id, list_id).await
+                        {
+                            break Some(reason);
+                        }
+                    }
+                    continue;
+                }
+                let result = send_list_channel_update(
+                    &request,
+                    &app_data,
+                    &mut session,
+                    user_id,
+                    list_id,
+                    update,
+                )
+                .await;
+                if result.is_err() {
+                    break None;
+                }
+            }
+            _ = heartbeat.tick() => {
+                if last_message.elapsed() > LIST_CHANNEL_CLIENT_TIMEOUT {
+                    break None;
+                }
+                match user.credentials.are_valid(&app_data.pool, user_id).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        break Some(actix_ws::CloseReason {
+                            code: actix_ws::CloseCode::Policy,
+                            description: Some("invalid credentials".to_string()),
+                        });
+                    }
+                    Err(err) => log::error!("Failed to check credentials of list channel: {}", err),
+                }
+                // the list could have been deleted, which is not notified
+                if let Some(reason) = lost_list_channel_access(&app_data.pool, user_id, list_id).await {
+                    break Some(reason);
+                }
+                if let Err(err) =
+                    refresh_list_channel_presence(&app_data.pool, list_id, connection_id).await
+                {
+                    log::error!("Failed to refresh presence of list channel: {}", err);
+                }
+                if session.ping(b"").await.is_err() {
+                    break None;
+                }
+            }
+        }
+    };
+
+    // the connection is removed once it hasn't been refreshed for long enough, if this fails
+    if let Err(err) = leave_list_channel(&app_data.pool, connection_id).await {
+        log::error!("Failed to leave list channel: {}", err);
+    }
+    // fails if the connection is already closed, which is fine
+    let _ = session.close(close_reason).await;
+}
+
+// a websocket for collaborating on a list: the client receives the changes of its entries
+// and who else is connected, and can create, patch and delete entries and set its status
+pub(super) async fn get_list_channel(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    body: web::Payload,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    session_id: Option<ReqData<SessionId>>,
+    api_key_scope: Option<ReqData<ApiKeyScope>>,
+) -> HttpResponse {
+    let list_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let read_only = api_key_scope
+        .as_ref()
+        .is_some_and(|api_key_scope| api_key_scope.read_only);
+    let list_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_list(pool, user_id, list_id).await);
+    let Some(list) = list_option else {
+        return HttpResponse::NotFound().json("list not found");
+    };
+
+    let (response, session, messages) = match actix_ws::handle(&request, body) {
+        Ok(handshake) => handshake,
+        Err(err) => return err.error_response(),
+    };
+    let user = ListChannelUser {
+        id: user_id,
+        credentials: Credentials::of_request(session_id, api_key_scope),
+        read_only,
+    };
+    actix_web::rt::spawn(run_list_channel(
+        request, app_data, user, list, session, messages,
+    ));
+
+    response
+}
+
+const ACTIVITY_DEFAULT_LIMIT: i64 = 50;
+const ACTIVITY_MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize)]
+pub(super) struct GetGroupActivityQuery {
+    limit: Option<i64>,
+    // the id of the last event of the previous page
+    before: Option<i64>,
+}
+
+// the changes of all entries in the lists of the group, newest first
+pub(super) async fn get_group_activity(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetGroupActivityQuery>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let limit = query.limit.unwrap_or(ACTIVITY_DEFAULT_LIMIT);
+    if !(1..=ACTIVITY_MAX_LIMIT).contains(&limit) {
+        return HttpResponse::BadRequest()
+            .json(format!("limit must be between 1 and {ACTIVITY_MAX_LIMIT}"));
+    }
+    let is_member =
+        ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    let mut events = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            EntryEvent,
+            r#"select
+                ev.id, ev.entry_id, ev.list_id, l.group_id, ev.actor_id,
+                ev.kind as "kind: EntryEventKind", ev.changes, ev.created
+            from
+                entry_events as ev
+            inner join
+                lists as l
+                    on l.id = ev.list_id
+            where
+                l.group_id = $1
+                and ($2::bigint is null or ev.id < $2)
+            order by ev.id desc
+            limit $3"#,
+            group_id,
+            query.before,
+            // one more than requested, to know if there is a next page
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await
+    );
+
+    let mut response_builder = HttpResponse::Ok();
+    if events.len() as i64 > limit {
+        events.truncate(limit as usize);
+        let last_id = events[events.len() - 1].id;
+        let link = ok_or_log_and_respond_internal_server_error!(next_page_link(
+            &request,
+            (
+                resource_name!("/groups/{id}/activity"),
+                &[group_id.to_string()]
+            ),
+            "before",
+            &last_id.to_string(),
+        ));
+        response_builder.insert_header((header::LINK, link));
+    }
+
+    let body = all_ok_or_log_and_respond_internal_server_error!(events
+        .iter()
+        .map(|event| event.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    response_builder.json(body)
+}
+
+// returns the archive if it contains entries of one of the user's groups or personal entries of the user
+async fn fetch_archive(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    archive_id: i64,
+) -> Result<Option<Archive>, sqlx::Error> {
+    sqlx::query_as!(
+        Archive,
+        r#"select
+            a.id, a.group_id, a.user_id, a.created_by, a.created,
+            (select count(*) from entries where archive_id = a.id) as "entry_count!"
+        from archives as a
+        left outer join users_groups_relations as ugr
+            on ugr.group_id = a.group_id
+            and ugr.user_id = $1
+        where a.id = $2 and (a.user_id = $1 or ugr.group_id is not null)"#,
+        user_id,
+        archive_id,
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// moves all bought entries of the group, or the personal entries of the user, into a new archive
+// exactly one of group_id and owner_id has to be set
+async fn archive_bought_entries(
+    request: &actix_web::HttpRequest,
+    pool: &Pool<Postgres>,
+    user_id: i64,
+    group_id: Option<i64>,
+    owner_id: Option<i64>,
+) -> HttpResponse {
+    let mut transaction = ok_or_log_and_respond_internal_server_error!(pool.begin().await);
+    let archive_id = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_scalar!(
+            r#"insert into archives (group_id, user_id, created_by) values ($1, $2, $3) returning id"#,
+            group_id,
+            owner_id,
+            user_id,
+        )
+        .fetch_one(&mut *transaction)
+        .await
+    );
+    let archived = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"update entries set archive_id = $1
+            where archive_id is null
+                and deleted is null
+                and bought is not null
+                and list_id in (
+                    select id from lists
+                    where group_id is not distinct from $2 and user_id is not distinct from $3
+                )"#,
+            archive_id,
+            group_id,
+            owner_id,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    // the transaction is rolled back when it is dropped, so no empty archive is left behind
+    if archived.rows_affected() == 0 {
+        return HttpResponse::Conflict().json("there are no bought entries to archive");
+    }
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"insert into entry_events (entry_id, list_id, actor_id, kind, changes)
+            select id, list_id, $2, $3, '{}' from entries where archive_id = $1 order by id"#,
+            archive_id,
+            user_id,
+            EntryEventKind::Archived as EntryEventKind,
+        )
+        .execute(&mut *transaction)
+        .await
+    );
+    let archive_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_archive(&mut *transaction, user_id, archive_id).await
+    );
+    ok_or_log_and_respond_internal_server_error!(transaction.commit().await);
+    let Some(archive) = archive_option else {
+        log::error!("Archive {} was not found after creating it", archive_id);
+        return HttpResponse::InternalServerError().json("internal server error");
+    };
+
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(archive.rest_resource(request));
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+// lists the archives of the group or of the user, newest first
+// exactly one of group_id and owner_id has to be set
+async fn respond_with_archives(
+    request: &actix_web::HttpRequest,
+    pool: &Pool<Postgres>,
+    group_id: Option<i64>,
+    owner_id: Option<i64>,
+) -> HttpResponse {
+    let archives = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Archive,
+            r#"select
+                a.id, a.group_id, a.user_id, a.created_by, a.created,
+                (select count(*) from entries where archive_id = a.id) as "entry_count!"
+            from archives as a
+            where a.group_id is not distinct from $1 and a.user_id is not distinct from $2
+            order by a.id desc"#,
+            group_id,
+            owner_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(archives
+        .iter()
+        .map(|archive| archive.rest_resource(request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+pub(super) async fn get_group_archives(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let pool = &app_data.pool;
+    let is_member = ok_or_log_and_respond_internal_server_error!(
+        is_member(pool, user_id.into_inner(), group_id).await
+    );
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    respond_with_archives(&request, pool, Some(group_id), None).await
+}
+
+// every member can archive the bought entries, just like they could delete them
+pub(super) async fn post_group_archive(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_member =
+        ok_or_log_and_respond_internal_server_error!(is_member(pool, user_id, group_id).await);
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    archive_bought_entries(&request, pool, user_id, Some(group_id), None).await
+}
+
+pub(super) async fn get_user_archives(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    respond_with_archives(&request, pool, None, Some(user_id)).await
+}
+
+pub(super) async fn post_user_archive(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    archive_bought_entries(&request, pool, user_id, None, Some(user_id)).await
+}
+
+pub(super) async fn get_archive_by_id(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let archive_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_archive(&app_data.pool, user_id.into_inner(), id.into_inner()).await
+    );
+    let Some(archive) = archive_option else {
+        return HttpResponse::NotFound().json("archive not found");
+    };
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(archive.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+pub(super) async fn get_archive_entries(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetEntriesQuery>,
+) -> HttpResponse {
+    let archive_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let archive_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_archive(pool, user_id, archive_id).await
+    );
+    if archive_option.is_none() {
+        return HttpResponse::NotFound().json("archive not found");
+    }
+
+    respond_with_entries(
+        &request,
+        pool,
+        user_id,
+        Some(EntryScope::Archive(archive_id)),
+        query.into_inner(),
+        (
+            resource_name!("/archives/{id}/entries"),
+            &[archive_id.to_string()],
+        ),
+    )
+    .await
+}
+
+const STORE_NAME_MAX_LENGTH: usize = 80;
+
+fn is_valid_store_name(name: &str) -> bool {
+    !name.trim().is_empty() && name.chars().count() <= STORE_NAME_MAX_LENGTH
+}
+
+// iso 4217 codes consist of three letters, lowercase ones are converted before they are stored
+fn is_valid_currency(currency: &str) -> bool {
+    currency.len() == 3 && currency.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+// entries and trips can only refer to stores of the group or user that owns their list
+async fn is_store_of_list_owner(
+    executor: impl PgExecutor<'_>,
+    store_id: i64,
+    list_id: i64,
+) -> Result<bool, sqlx::Error> {
+    Ok(sqlx::query!(
+        r#"select exists (
+            select 1 from stores as s
+            join lists as l on l.group_id = s.group_id or l.user_id = s.user_id
+            where s.id = $1 and l.id = $2
+        ) as "exists!: bool""#,
+        store_id,
+        list_id,
+    )
+    .fetch_one(executor)
+    .await?
+    .exists)
+}
+
+// returns the store if it is a personal store of the user or a store of one of their groups
+async fn fetch_store(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    store_id: i64,
+) -> Result<Option<Store>, sqlx::Error> {
+    sqlx::query_as!(
+        Store,
+        r#"select s.id, s.name, s.group_id, s.user_id, s.created
+        from stores as s
+        left outer join users_groups_relations as ugr
+            on ugr.group_id = s.group_id
+            and ugr.user_id = $1
+        where s.id = $2 and (s.user_id = $1 or ugr.group_id is not null)"#,
+        user_id,
+        store_id,
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+// lists the stores of the group or of the user, ordered by name
+// exactly one of group_id and owner_id has to be set
+async fn respond_with_stores(
+    request: &actix_web::HttpRequest,
+    pool: &Pool<Postgres>,
+    group_id: Option<i64>,
+    owner_id: Option<i64>,
+) -> HttpResponse {
+    let stores = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Store,
+            r#"select id, name, group_id, user_id, created
+            from stores
+            where group_id is not distinct from $1 and user_id is not distinct from $2
+            order by lower(name), id"#,
+            group_id,
+            owner_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(stores
+        .iter()
+        .map(|store| store.rest_resource(request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+#[derive(Deserialize)]
+pub(super) struct StoreRequestData {
+    name: String,
+}
+
+// exactly one of group_id and owner_id has to be set
+async fn insert_store(
+    request: &actix_web::HttpRequest,
+    pool: &Pool<Postgres>,
+    group_id: Option<i64>,
+    owner_id: Option<i64>,
+    payload: StoreRequestData,
+) -> HttpResponse {
+    if !is_valid_store_name(&payload.name) {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {STORE_NAME_MAX_LENGTH} characters"
+        ));
+    }
+
+    let store_result = sqlx::query_as!(
+        Store,
+        r#"insert into stores (name, group_id, user_id) values ($1, $2, $3)
+        returning id, name, group_id, user_id, created"#,
+        payload.name,
+        group_id,
+        owner_id,
+    )
+    .fetch_one(pool)
+    .await;
+    let store = match store_result {
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            return HttpResponse::Conflict().json("there already is a store with this name");
+        }
+        result => ok_or_log_and_respond_internal_server_error!(result),
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(store.rest_resource(request));
+
+    HttpResponse::Created().json(rest_resource)
+}
+
+pub(super) async fn get_group_stores(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let pool = &app_data.pool;
+    let is_member = ok_or_log_and_respond_internal_server_error!(
+        is_member(pool, user_id.into_inner(), group_id).await
+    );
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    respond_with_stores(&request, pool, Some(group_id), None).await
+}
+
+// every member of a group can add stores to it, just like lists
+pub(super) async fn post_group_store(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<StoreRequestData>,
+) -> HttpResponse {
+    let group_id = id.into_inner();
+    let pool = &app_data.pool;
+    let is_member = ok_or_log_and_respond_internal_server_error!(
+        is_member(pool, user_id.into_inner(), group_id).await
+    );
+    if !is_member {
+        return HttpResponse::NotFound().json("group not found");
+    }
+
+    insert_store(&request, pool, Some(group_id), None, payload.into_inner()).await
+}
+
+pub(super) async fn get_user_stores(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    respond_with_stores(&request, pool, None, Some(user_id)).await
+}
+
+pub(super) async fn post_user_store(
+    request: actix_web::HttpRequest,
+    identifier: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<StoreRequestData>,
+) -> HttpResponse {
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let is_own_identifier = ok_or_log_and_respond_internal_server_error!(
+        is_own_identifier(pool, user_id, &identifier).await
+    );
+    if !is_own_identifier {
+        return HttpResponse::NotFound().json("user not found");
+    }
+
+    insert_store(&request, pool, None, Some(user_id), payload.into_inner()).await
+}
+
+pub(super) async fn get_store_by_id(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let store_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_store(&app_data.pool, user_id.into_inner(), id.into_inner()).await
+    );
+    let Some(store) = store_option else {
+        return HttpResponse::NotFound().json("store not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(store.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+pub(super) async fn patch_store(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<StoreRequestData>,
+) -> HttpResponse {
+    if !is_valid_store_name(&payload.name) {
+        return HttpResponse::BadRequest().json(format!(
+            "name must not be empty and must not be longer than {STORE_NAME_MAX_LENGTH} characters"
+        ));
+    }
+    let store_id = id.into_inner();
+    let pool = &app_data.pool;
+    let store_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_store(pool, user_id.into_inner(), store_id).await
+    );
+    if store_option.is_none() {
+        return HttpResponse::NotFound().json("store not found");
+    }
+
+    let store_result = sqlx::query_as!(
+        Store,
+        r#"update stores set name = $1 where id = $2
+        returning id, name, group_id, user_id, created"#,
+        payload.name,
+        store_id,
+    )
+    .fetch_optional(pool)
+    .await;
+    let store_option = match store_result {
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            return HttpResponse::Conflict().json("there already is a store with this name");
+        }
+        result => ok_or_log_and_respond_internal_server_error!(result),
+    };
+    // the store could have been deleted between the check and the update
+    let Some(store) = store_option else {
+        return HttpResponse::NotFound().json("store not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(store.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+// the entries and trips of a deleted store keep their prices, they just lose the store
+pub(super) async fn delete_store(
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let store_id = id.into_inner();
+    let pool = &app_data.pool;
+    let store_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_store(pool, user_id.into_inner(), store_id).await
+    );
+    if store_option.is_none() {
+        return HttpResponse::NotFound().json("store not found");
+    }
+
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(r#"delete from stores where id = $1"#, store_id)
+            .execute(pool)
+            .await
+    );
+
+    HttpResponse::NoContent().finish()
+}
+
+// returns the trip if it is on one of the user's personal lists or on a list of one of their groups
+async fn fetch_trip(
+    executor: impl PgExecutor<'_>,
+    user_id: i64,
+    trip_id: i64,
+) -> Result<Option<Trip>, sqlx::Error> {
+    sqlx::query_as!(
+        Trip,
+        r#"select
+            t.id, t.list_id, l.group_id, t.shopper_id, t.store_id, t.started, t.ended,
+            extract(epoch from coalesce(t.ended, now()) - t.started)::bigint as "duration_seconds!",
+            -- the total is a string in the json, so it isn't turned into a float on the way
+            (select coalesce(json_agg(totals order by currency nulls last), '[]')
+                from (select currency, sum(price)::text as total
+                    from entries
+                    where trip_id = t.id and deleted is null and price is not null
+                    group by currency) as totals)
+                as "totals!: sqlx::types::Json<Vec<TripTotal>>",
+            (select count(*) from entries where trip_id = t.id and deleted is null)
+                as "entry_count!"
+        from trips as t
+        join lists as l on l.id = t.list_id
+        left outer join users_groups_relations as ugr
+            on ugr.group_id = l.group_id
+            and ugr.user_id = $1
+        where t.id = $2 and (l.user_id = $1 or ugr.group_id is not null)"#,
+        user_id,
+        trip_id,
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+pub(super) async fn get_list_trips(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let list_id = id.into_inner();
+    let pool = &app_data.pool;
+    let list_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_list(pool, user_id.into_inner(), list_id).await
+    );
+    if list_option.is_none() {
+        return HttpResponse::NotFound().json("list not found");
+    }
+
+    let trips = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            Trip,
+            r#"select
+                t.id, t.list_id, l.group_id, t.shopper_id, t.store_id, t.started, t.ended,
+                extract(epoch from coalesce(t.ended, now()) - t.started)::bigint as "duration_seconds!",
+                -- the total is a string in the json, so it isn't turned into a float on the way
+                (select coalesce(json_agg(totals order by currency nulls last), '[]')
+                    from (select currency, sum(price)::text as total
+                        from entries
+                        where trip_id = t.id and deleted is null and price is not null
+                        group by currency) as totals)
+                    as "totals!: sqlx::types::Json<Vec<TripTotal>>",
+                (select count(*) from entries where trip_id = t.id and deleted is null)
+                    as "entry_count!"
+            from trips as t
+            join lists as l on l.id = t.list_id
+            where t.list_id = $1
+            order by t.id desc"#,
+            list_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let body = all_ok_or_log_and_respond_internal_server_error!(trips
+        .iter()
+        .map(|trip| trip.rest_resource(&request))
+        .collect::<Vec<_>>());
+
+    HttpResponse::Ok().json(body)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PostTripRequestData {
+    store_id: Option<i64>,
+}
+
+// everyone who can see a list can go shopping for it,
+// the entries they buy on it until the trip is ended become part of the trip
+pub(super) async fn post_list_trip(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PostTripRequestData>,
+) -> HttpResponse {
+    let list_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let list_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_list(pool, user_id, list_id).await);
+    if list_option.is_none() {
+        return HttpResponse::NotFound().json("list not found");
+    }
+    if let Some(store_id) = payload.store_id {
+        let is_store_of_list_owner = ok_or_log_and_respond_internal_server_error!(
+            is_store_of_list_owner(pool, store_id, list_id).await
+        );
+        if !is_store_of_list_owner {
+            return HttpResponse::BadRequest()
+                .json("the store has to belong to the owner of the list");
+        }
+    }
+
+    let trip_id_result = sqlx::query_scalar!(
+        r#"insert into trips (list_id, shopper_id, store_id) values ($1, $2, $3) returning id"#,
+        list_id,
+        user_id,
+        payload.store_id,
+    )
+    .fetch_one(pool)
+    .await;
+    let trip_id = match trip_id_result {
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            return HttpResponse::Conflict().json("you are already on a trip");
+        }
+        result => ok_or_log_and_respond_internal_server_error!(result),
+    };
+    let trip_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_trip(pool, user_id, trip_id).await);
+    let Some(trip) = trip_option else {
+        return HttpResponse::NotFound().json("trip not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(trip.rest_resource(&request));
 
     HttpResponse::Created().json(rest_resource)
 }
+
+pub(super) async fn get_trip_by_id(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let trip_option = ok_or_log_and_respond_internal_server_error!(
+        fetch_trip(&app_data.pool, user_id.into_inner(), id.into_inner()).await
+    );
+    let Some(trip) = trip_option else {
+        return HttpResponse::NotFound().json("trip not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(trip.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+#[derive(Deserialize)]
+pub(super) struct PatchTripRequestData {
+    store_id: Option<i64>,
+}
+
+// only the shopper can change the store, even after the trip has ended
+pub(super) async fn patch_trip(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    payload: Json<PatchTripRequestData>,
+) -> HttpResponse {
+    let trip_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let trip_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_trip(pool, user_id, trip_id).await);
+    let Some(trip) = trip_option else {
+        return HttpResponse::NotFound().json("trip not found");
+    };
+    if trip.shopper_id != Some(user_id) {
+        return HttpResponse::Forbidden().json("only the shopper can change the trip");
+    }
+    if let Some(store_id) = payload.store_id {
+        let is_store_of_list_owner = ok_or_log_and_respond_internal_server_error!(
+            is_store_of_list_owner(pool, store_id, trip.list_id).await
+        );
+        if !is_store_of_list_owner {
+            return HttpResponse::BadRequest()
+                .json("the store has to belong to the owner of the list");
+        }
+    }
+
+    ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"update trips set store_id = $1 where id = $2"#,
+            payload.store_id,
+            trip_id,
+        )
+        .execute(pool)
+        .await
+    );
+    let trip_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_trip(pool, user_id, trip_id).await);
+    // the trip could have been deleted together with its list in the meantime
+    let Some(trip) = trip_option else {
+        return HttpResponse::NotFound().json("trip not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(trip.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+pub(super) async fn end_trip(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+) -> HttpResponse {
+    let trip_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let trip_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_trip(pool, user_id, trip_id).await);
+    let Some(trip) = trip_option else {
+        return HttpResponse::NotFound().json("trip not found");
+    };
+    if trip.shopper_id != Some(user_id) {
+        return HttpResponse::Forbidden().json("only the shopper can end the trip");
+    }
+
+    let ended = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query!(
+            r#"update trips set ended = now() where id = $1 and ended is null"#,
+            trip_id,
+        )
+        .execute(pool)
+        .await
+    );
+    if ended.rows_affected() == 0 {
+        return HttpResponse::Conflict().json("the trip has already ended");
+    }
+    let trip_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_trip(pool, user_id, trip_id).await);
+    let Some(trip) = trip_option else {
+        return HttpResponse::NotFound().json("trip not found");
+    };
+    let rest_resource = ok_or_log_and_respond_internal_server_error!(trip.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+pub(super) async fn get_trip_entries(
+    request: actix_web::HttpRequest,
+    id: web::Path<i64>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetEntriesQuery>,
+) -> HttpResponse {
+    let trip_id = id.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let trip_option =
+        ok_or_log_and_respond_internal_server_error!(fetch_trip(pool, user_id, trip_id).await);
+    if trip_option.is_none() {
+        return HttpResponse::NotFound().json("trip not found");
+    }
+
+    respond_with_entries(
+        &request,
+        pool,
+        user_id,
+        Some(EntryScope::Trip(trip_id)),
+        query.into_inner(),
+        (
+            resource_name!("/trips/{id}/entries"),
+            &[trip_id.to_string()],
+        ),
+    )
+    .await
+}
+
+const PRICES_DEFAULT_LIMIT: i64 = 50;
+const PRICES_MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize)]
+pub(super) struct GetProductPricesQuery {
+    group_id: Option<GroupIdFilter>,
+    store_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+// the prices that have been paid for a product on the lists the user can see,
+// products are matched case insensitively, so "water" also finds "Water"
+pub(super) async fn get_product_prices(
+    request: actix_web::HttpRequest,
+    name: web::Path<String>,
+    app_data: web::Data<AppData>,
+    user_id: ReqData<i64>,
+    query: web::Query<GetProductPricesQuery>,
+) -> HttpResponse {
+    let limit = query.limit.unwrap_or(PRICES_DEFAULT_LIMIT);
+    if !(1..=PRICES_MAX_LIMIT).contains(&limit) {
+        return HttpResponse::BadRequest()
+            .json(format!("limit must be between 1 and {PRICES_MAX_LIMIT}"));
+    }
+    let product = name.into_inner();
+    let user_id = user_id.into_inner();
+    let pool = &app_data.pool;
+    let (personal_only, group_id) = match query.group_id {
+        None => (false, None),
+        Some(GroupIdFilter::Personal) => (true, None),
+        Some(GroupIdFilter::Group(group_id)) => (false, Some(group_id)),
+    };
+
+    // archived entries are part of the history, deleted ones are not
+    let averages = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            ProductPriceAverage,
+            r#"select
+                e.currency, e.unit, e.store_id, s.name as "store?",
+                round(avg(e.price), 2) as "average_price!",
+                round(avg(e.price / nullif(e.amount::numeric, 0)), 4) as average_unit_price,
+                count(*) as "count!"
+            from entries as e
+            join lists as l on l.id = e.list_id
+            left outer join users_groups_relations as ugr
+                on ugr.group_id = l.group_id
+                and ugr.user_id = $1
+            left outer join stores as s on s.id = e.store_id
+            where (l.user_id = $1 or ugr.group_id is not null)
+                and e.deleted is null
+                and e.bought is not null
+                and e.price is not null
+                and lower(e.product) = lower($2)
+                and ($3 is false or l.user_id is not null)
+                and ($4::bigint is null or l.group_id = $4)
+                and ($5::bigint is null or e.store_id = $5)
+            group by grouping sets ((e.currency, e.unit), (e.currency, e.unit, e.store_id, s.name))
+            -- entries without a store are only part of the average over all stores
+            having grouping(e.store_id) = 1 or e.store_id is not null
+            order by e.currency, e.unit, e.store_id nulls first"#,
+            user_id,
+            product,
+            personal_only,
+            group_id,
+            query.store_id,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let history = ok_or_log_and_respond_internal_server_error!(
+        sqlx::query_as!(
+            ProductPrice,
+            r#"select
+                e.id as entry_id, e.price as "price!", e.currency, e.amount, e.unit,
+                e.store_id, s.name as "store?", e.bought as "bought!", e.bought_by,
+                e.list_id, l.group_id
+            from entries as e
+            join lists as l on l.id = e.list_id
+            left outer join users_groups_relations as ugr
+                on ugr.group_id = l.group_id
+                and ugr.user_id = $1
+            left outer join stores as s on s.id = e.store_id
+            where (l.user_id = $1 or ugr.group_id is not null)
+                and e.deleted is null
+                and e.bought is not null
+                and e.price is not null
+                and lower(e.product) = lower($2)
+                and ($3 is false or l.user_id is not null)
+                and ($4::bigint is null or l.group_id = $4)
+                and ($5::bigint is null or e.store_id = $5)
+            order by e.bought desc, e.id desc
+            limit $6"#,
+            user_id,
+            product,
+            personal_only,
+            group_id,
+            query.store_id,
+            limit,
+        )
+        .fetch_all(pool)
+        .await
+    );
+    let product_prices = ProductPrices {
+        product,
+        averages,
+        history,
+    };
+    let rest_resource =
+        ok_or_log_and_respond_internal_server_error!(product_prices.rest_resource(&request));
+
+    HttpResponse::Ok().json(rest_resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(sort: &str, value: Option<&str>, id: i64) -> String {
+        EntriesCursor {
+            sort: sort.to_string(),
+            value: value.map(str::to_string),
+            id,
+        }
+        .encode()
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_usernames_that_start_with_a_letter_or_underscore() {
+        assert!(is_valid_username("alice"));
+        assert!(is_valid_username("_bob"));
+        assert!(is_valid_username("Carol_2-b"));
+        assert!(is_valid_username(&"a".repeat(USERNAME_MAX_LENGTH)));
+    }
+
+    #[test]
+    fn rejects_usernames_that_could_be_mistaken_for_ids() {
+        assert!(!is_valid_username("1"));
+        assert!(!is_valid_username("42alice"));
+    }
+
+    #[test]
+    fn rejects_empty_long_or_special_usernames() {
+        assert!(!is_valid_username(""));
+        assert!(!is_valid_username("-alice"));
+        assert!(!is_valid_username("alice bob"));
+        assert!(!is_valid_username("alice/bob"));
+        assert!(!is_valid_username("älice"));
+        assert!(!is_valid_username(&"a".repeat(USERNAME_MAX_LENGTH + 1)));
+    }
+
+    #[test]
+    fn parses_sorts_with_an_optional_descending_prefix() {
+        assert_eq!(
+            EntrySort::parse("created").map(|sort| (sort.column, sort.descending)),
+            Some((EntrySortColumn::Created, false))
+        );
+        assert_eq!(
+            EntrySort::parse("-price").map(|sort| (sort.column, sort.descending)),
+            Some((EntrySortColumn::Price, true))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_sorts() {
+        assert!(EntrySort::parse("password").is_none());
+        assert!(EntrySort::parse("--created").is_none());
+        assert!(EntrySort::parse("").is_none());
+    }
+
+    #[test]
+    fn decodes_the_cursors_it_encodes() {
+        let created = Utc::now();
+        assert_eq!(
+            EntriesCursor::decode(
+                &encode("-created", Some(&created.to_rfc3339()), 4),
+                "-created",
+                EntrySortColumn::Created
+            ),
+            Some(DecodedEntriesCursor {
+                value: Some(CursorValue::Timestamp(created)),
+                id: 4
+            })
+        );
+        assert_eq!(
+            EntriesCursor::decode(
+                &encode("price", Some("1.99"), 1),
+                "price",
+                EntrySortColumn::Price
+            ),
+            Some(DecodedEntriesCursor {
+                value: Some(CursorValue::Numeric("1.99".parse().unwrap())),
+                id: 1
+            })
+        );
+        assert_eq!(
+            EntriesCursor::decode(
+                &encode("product", Some("milk"), 9),
+                "product",
+                EntrySortColumn::Product
+            ),
+            Some(DecodedEntriesCursor {
+                value: Some(CursorValue::Text("milk".to_string())),
+                id: 9
+            })
+        );
+        assert_eq!(
+            EntriesCursor::decode(
+                &encode("bought_by", None, 9),
+                "bought_by",
+                EntrySortColumn::BoughtBy
+            ),
+            Some(DecodedEntriesCursor { value: None, id: 9 })
+        );
+    }
+
+    #[test]
+    fn rejects_cursors_of_another_sort() {
+        assert!(EntriesCursor::decode(
+            &encode("created", Some("2026-10-18T12:00:00+00:00"), 1),
+            "-created",
+            EntrySortColumn::Created
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn rejects_cursors_whose_value_doesnt_fit_the_column() {
+        assert!(EntriesCursor::decode(
+            &encode("created", Some("abc"), 1),
+            "created",
+            EntrySortColumn::Created
+        )
+        .is_none());
+        assert!(EntriesCursor::decode(
+            &encode("list_id", Some("1 or true"), 1),
+            "list_id",
+            EntrySortColumn::ListId
+        )
+        .is_none());
+        assert!(EntriesCursor::decode(
+            &encode("price", Some("NaN-ish"), 1),
+            "price",
+            EntrySortColumn::Price
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        assert!(EntriesCursor::decode("not base64!", "id", EntrySortColumn::Id).is_none());
+        assert!(EntriesCursor::decode(
+            &URL_SAFE_NO_PAD.encode(b"{\"sort\":\"id\"}"),
+            "id",
+            EntrySortColumn::Id
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn escapes_the_wildcards_of_like() {
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a_b"), "a\\_b");
+        assert_eq!(escape_like("c:\\"), "c:\\\\");
+        assert_eq!(escape_like("milk"), "milk");
+    }
+
+    fn entry() -> Entry {
+        Entry {
+            id: 1,
+            product: "Milk".to_string(),
+            amount: 2.0,
+            unit: "l".to_string(),
+            note: None,
+            created: Utc::now(),
+            bought: None,
+            bought_by: None,
+            deleted: None,
+            deleted_by: None,
+            archive_id: None,
+            trip_id: None,
+            price: None,
+            currency: None,
+            store_id: None,
+            version: 1,
+            user_id: 1,
+            list_id: 4,
+            group_id: Some(1),
+        }
+    }
+
+    #[test]
+    fn records_only_the_changed_fields() {
+        let old = entry();
+        let new = Entry {
+            amount: 3.0,
+            note: Some("low fat".to_string()),
+            version: 2,
+            ..old.clone()
+        };
+        assert_eq!(
+            entry_changes(Some(&old), Some(&new)),
+            json!({
+                "amount": { "old": 2.0, "new": 3.0 },
+                "note": { "old": null, "new": "low fat" },
+            })
+        );
+    }
+
+    #[test]
+    fn records_nothing_for_untracked_fields() {
+        let old = entry();
+        let new = Entry {
+            list_id: 6,
+            archive_id: Some(1),
+            version: 2,
+            ..old.clone()
+        };
+        assert_eq!(entry_changes(Some(&old), Some(&new)), json!({}));
+    }
+
+    #[test]
+    fn records_all_set_values_of_created_and_deleted_entries() {
+        let entry = entry();
+        assert_eq!(
+            entry_changes(None, Some(&entry)),
+            json!({
+                "product": { "old": null, "new": "Milk" },
+                "amount": { "old": null, "new": 2.0 },
+                "unit": { "old": null, "new": "l" },
+            })
+        );
+        assert_eq!(
+            entry_changes(Some(&entry), None),
+            json!({
+                "product": { "old": "Milk", "new": null },
+                "amount": { "old": 2.0, "new": null },
+                "unit": { "old": "l", "new": null },
+            })
+        );
+    }
+
+    #[test]
+    fn store_names_cant_be_blank_or_too_long() {
+        assert!(is_valid_store_name("Corner Shop"));
+        assert!(is_valid_store_name(&"ä".repeat(STORE_NAME_MAX_LENGTH)));
+        assert!(!is_valid_store_name(""));
+        assert!(!is_valid_store_name("  "));
+        assert!(!is_valid_store_name(&"a".repeat(STORE_NAME_MAX_LENGTH + 1)));
+    }
+
+    #[test]
+    fn currencies_are_three_letters() {
+        assert!(is_valid_currency("EUR"));
+        assert!(is_valid_currency("usd"));
+        assert!(!is_valid_currency("EU"));
+        assert!(!is_valid_currency("EURO"));
+        assert!(!is_valid_currency("E1R"));
+        assert!(!is_valid_currency("€"));
+        assert!(!is_valid_currency(""));
+    }
+
+    #[test]
+    fn entries_match_the_etag_of_their_version() {
+        let entry = Entry {
+            version: 3,
+            ..entry()
+        };
+        assert!(check_entry_precondition(&entry, None).is_ok());
+        assert!(check_entry_precondition(&entry, Some(&header::IfMatch::Any)).is_ok());
+        assert!(check_entry_precondition(&entry, version_if_match(Some(3)).as_ref()).is_ok());
+        assert!(matches!(
+            check_entry_precondition(&entry, version_if_match(Some(2)).as_ref()),
+            Err(EntryOperationError::PreconditionFailed(current)) if current.version == 3
+        ));
+    }
+
+    #[test]
+    fn entries_dont_match_weak_etags() {
+        let if_match = header::IfMatch::Items(vec![header::EntityTag::new_weak("3".to_string())]);
+        let entry = Entry {
+            version: 3,
+            ..entry()
+        };
+        assert!(check_entry_precondition(&entry, Some(&if_match)).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use actix_web::web;
+use serde::Deserialize;
+use sqlx::postgres::PgListener;
+use tokio::sync::broadcast;
+
+use super::models::EntryEventKind;
+use crate::AppData;
+
+// updates that are not received by a subscriber within this many further updates are lost for it,
+// the subscriber is told how many it missed instead
+const CHANNEL_CAPACITY: usize = 1024;
+// has to match the channel the triggers of the database notify
+const NOTIFICATION_CHANNEL: &str = "shoppinglist_changes";
+// how long to wait before listening again, after the listener failed
+const RELISTEN_DELAY: Duration = Duration::from_secs(5);
+
+// only identifies the entry, every subscriber fetches it on its own,
+// because it may only see the entries of its own groups
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub(super) struct EntryChange {
+    pub entry_id: i64,
+    pub list_id: i64,
+    // the group of the list of the entry, so streams of other groups can skip the change
+    pub group_id: Option<i64>,
+    // the owner of the list of the entry, if it is a personal list
+    pub user_id: Option<i64>,
+    pub kind: EntryEventKind,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(super) enum LiveUpdate {
+    Entry(EntryChange),
+    // someone connected to the channel of the list, disconnected or changed their status,
+    // through any instance of the server
+    Presence { list_id: i64 },
+    // the group or the membership of the user changed, so they may have lost access to its lists
+    // without a user the change concerns all members
+    Access { group_id: i64, user_id: Option<i64> },
+    // notifications of the database may have been lost while listening again,
+    // so the subscribers have to fetch everything again, like when they lag behind
+    Lagged,
+}
+
+// the payloads the triggers of the database send to NOTIFICATION_CHANNEL
+#[derive(Deserialize)]
+#[serde(tag = "table", rename_all = "snake_case")]
+enum DatabaseChange {
+    Entries(EntryChange),
+    Groups { group_id: i64 },
+    UsersGroupsRelations { group_id: i64, user_id: i64 },
+    ListPresences { list_id: i64 },
+}
+
+impl From<DatabaseChange> for LiveUpdate {
+    fn from(change: DatabaseChange) -> Self {
+        match change {
+            DatabaseChange::Entries(change) => LiveUpdate::Entry(change),
+            DatabaseChange::Groups { group_id } => LiveUpdate::Access {
+                group_id,
+                user_id: None,
+            },
+            DatabaseChange::UsersGroupsRelations { group_id, user_id } => LiveUpdate::Access {
+                group_id,
+                user_id: Some(user_id),
+            },
+            DatabaseChange::ListPresences { list_id } => LiveUpdate::Presence { list_id },
+        }
+    }
+}
+
+// distributes the changes of the database to the open event streams and list channels
+pub struct LiveUpdates {
+    sender: broadcast::Sender<LiveUpdate>,
+}
+
+impl Default for LiveUpdates {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl LiveUpdates {
+    fn send(&self, update: LiveUpdate) {
+        // sending only fails if nobody is subscribed, so nobody missed the update
+        let _ = self.sender.send(update);
+    }
+
+    pub(super) fn subscribe(&self) -> broadcast::Receiver<LiveUpdate> {
+        self.sender.subscribe()
+    }
+}
+
+async fn listen(
+    pool: &sqlx::PgPool,
+    live_updates: &LiveUpdates,
+    notifications_lost: bool,
+) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(NOTIFICATION_CHANNEL).await?;
+    if notifications_lost {
+        live_updates.send(LiveUpdate::Lagged);
+    }
+    loop {
+        // notifications sent while the connection is lost can't be received anymore
+        let Some(notification) = listener.try_recv().await? else {
+            log::warn!("Lost the connection for database notifications, reconnecting");
+            // the listener would only reconnect on the next receive, but the subscribers
+            // should only fetch everything again once no further notification can be lost
+            sqlx::query("select 1").execute(&mut listener).await?;
+            live_updates.send(LiveUpdate::Lagged);
+            continue;
+        };
+        match serde_json::from_str::<DatabaseChange>(notification.payload()) {
+            Ok(change) => live_updates.send(change.into()),
+            Err(err) => log::error!(
+                "Failed to parse database notification {}: {}",
+                notification.payload(),
+                err
+            ),
+        }
+    }
+}
+
+// forwards the changes of the database to the clients connected to this instance,
+// no matter which instance of the server made them
+// runs until the server stops
+pub async fn forward_database_changes(app_data: web::Data<AppData>) {
+    let mut notifications_lost = false;
+    loop {
+        if let Err(err) = listen(&app_data.pool, &app_data.live_updates, notifications_lost).await {
+            log::error!("Failed to listen for database notifications: {}", err);
+        }
+        notifications_lost = true;
+        actix_web::rt::time::sleep(RELISTEN_DELAY).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(payload: &str) -> Option<LiveUpdate> {
+        serde_json::from_str::<DatabaseChange>(payload)
+            .ok()
+            .map(LiveUpdate::from)
+    }
+
+    #[test]
+    fn parses_changes_of_entries() {
+        let update = parse(
+            r#"{"table":"entries","entry_id":1,"list_id":4,"group_id":1,"user_id":null,"kind":"bought"}"#,
+        );
+        assert!(matches!(
+            update,
+            Some(LiveUpdate::Entry(EntryChange {
+                entry_id: 1,
+                list_id: 4,
+                group_id: Some(1),
+                user_id: None,
+                kind: EntryEventKind::Bought,
+            }))
+        ));
+    }
+
+    #[test]
+    fn parses_purges_of_entries_on_personal_lists() {
+        let update = parse(
+            r#"{"table":"entries","entry_id":2,"list_id":2,"group_id":null,"user_id":1,"kind":"purged"}"#,
+        );
+        assert!(matches!(
+            update,
+            Some(LiveUpdate::Entry(EntryChange {
+                group_id: None,
+                user_id: Some(1),
+                kind: EntryEventKind::Purged,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn changes_of_groups_concern_all_members() {
+        assert!(matches!(
+            parse(r#"{"table":"groups","group_id":1}"#),
+            Some(LiveUpdate::Access {
+                group_id: 1,
+                user_id: None
+            })
+        ));
+    }
+
+    #[test]
+    fn changes_of_memberships_concern_their_user() {
+        assert!(matches!(
+            parse(r#"{"table":"users_groups_relations","group_id":1,"user_id":2}"#),
+            Some(LiveUpdate::Access {
+                group_id: 1,
+                user_id: Some(2)
+            })
+        ));
+    }
+
+    #[test]
+    fn parses_changes_of_presences() {
+        assert!(matches!(
+            parse(r#"{"table":"list_presences","list_id":4}"#),
+            Some(LiveUpdate::Presence { list_id: 4 })
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_tables_and_incomplete_payloads() {
+        assert!(parse(r#"{"table":"users","id":1}"#).is_none());
+        assert!(parse(r#"{"table":"entries","entry_id":1}"#).is_none());
+        assert!(parse(r#"{"group_id":1}"#).is_none());
+        assert!(parse("not json").is_none());
+    }
+}
@@ -7,6 +7,8 @@ macro_rules! resource_name {
 }
 
 mod handlers;
+mod live;
 mod models;
 mod routes;
-pub use routes::configure_routes;
+pub use live::{forward_database_changes, LiveUpdates};
+pub use routes::{configure_public_routes, configure_routes};
@@ -1,7 +1,8 @@
-use actix_web::error::UrlGenerationError;
+use actix_web::{error::UrlGenerationError, http::header::EntityTag};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
-use sqlx::prelude::FromRow;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json};
 
 #[derive(Serialize)]
 pub(super) struct RestResource<'a, T: 'a + Serialize> {
@@ -25,7 +26,7 @@ impl User {
     pub fn rest_resource(
         &self,
         request: &actix_web::HttpRequest,
-    ) -> Result<RestResource<User>, UrlGenerationError> {
+    ) -> Result<RestResource<'_, User>, UrlGenerationError> {
         let id_string_array = [self.id.to_string()];
 
         let self_resource_name = resource_name!("/users/{identifier}");
@@ -51,9 +52,57 @@ impl User {
                 )
             })?;
         let groups_username_url = request.url_for(groups_resource_name, [&self.username])?;
+        let entries_resource_name = resource_name!("/users/{identifier}/entries");
+        let entries_id_url = request
+            .url_for(entries_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    entries_resource_name,
+                )
+            })?;
+        let entries_username_url = request.url_for(entries_resource_name, [&self.username])?;
+        let lists_resource_name = resource_name!("/users/{identifier}/lists");
+        let lists_id_url = request
+            .url_for(lists_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    lists_resource_name,
+                )
+            })?;
+        let lists_username_url = request.url_for(lists_resource_name, [&self.username])?;
+        let archives_resource_name = resource_name!("/users/{identifier}/archives");
+        let archives_id_url = request
+            .url_for(archives_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    archives_resource_name,
+                )
+            })?;
+        let archives_username_url = request.url_for(archives_resource_name, [&self.username])?;
+        let stores_resource_name = resource_name!("/users/{identifier}/stores");
+        let stores_id_url = request
+            .url_for(stores_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    stores_resource_name,
+                )
+            })?;
+        let stores_username_url = request.url_for(stores_resource_name, [&self.username])?;
         let sub_resources = Some(vec![
             groups_id_url.to_string(),
             groups_username_url.to_string(),
+            entries_id_url.to_string(),
+            entries_username_url.to_string(),
+            lists_id_url.to_string(),
+            lists_username_url.to_string(),
+            archives_id_url.to_string(),
+            archives_username_url.to_string(),
+            stores_id_url.to_string(),
+            stores_username_url.to_string(),
         ]);
 
         Ok(RestResource {
@@ -64,6 +113,60 @@ impl User {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "group_role", rename_all = "lowercase")]
+pub(super) enum GroupRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl GroupRole {
+    // renaming and deleting the group as well as adding and removing members
+    pub fn can_manage_group(self) -> bool {
+        matches!(self, GroupRole::Owner | GroupRole::Admin)
+    }
+
+    // owners can change the role of any member, including other owners
+    pub fn can_change_roles(self) -> bool {
+        self == GroupRole::Owner
+    }
+
+    // admins may only remove members, not other admins or owners
+    pub fn can_remove(self, other: GroupRole) -> bool {
+        match self {
+            GroupRole::Owner => true,
+            GroupRole::Admin => other == GroupRole::Member,
+            GroupRole::Member => false,
+        }
+    }
+}
+
+// a user as seen in the context of one of their groups
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct GroupMember {
+    #[serde(flatten)]
+    pub user: User,
+    pub role: GroupRole,
+}
+
+impl GroupMember {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, GroupMember>, UrlGenerationError> {
+        // a member has the same links as the user itself
+        let user_rest_resource = self.user.rest_resource(request)?;
+
+        Ok(RestResource {
+            resource: self,
+            links: user_rest_resource.links,
+            sub_resources: user_rest_resource.sub_resources,
+        })
+    }
+}
+
 #[derive(Serialize)]
 pub(super) struct Group {
     pub id: i64,
@@ -71,10 +174,12 @@ pub(super) struct Group {
 }
 
 impl Group {
+    // the invites are only listed for the members who can see them, depending on their role
     pub fn rest_resource(
         &self,
         request: &actix_web::HttpRequest,
-    ) -> Result<RestResource<Group>, UrlGenerationError> {
+        role: GroupRole,
+    ) -> Result<RestResource<'_, Group>, UrlGenerationError> {
         let id_string_array = [self.id.to_string()];
         let self_resource_name = resource_name!("/groups/{id}");
         let self_id_url = request
@@ -95,12 +200,76 @@ impl Group {
                     users_resource_name,
                 );
             })?;
-        let sub_resources = Some(vec![users_id_url.to_string()]);
+        let invites_resource_name = resource_name!("/groups/{id}/invites");
+        let invites_id_url = request
+            .url_for(invites_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    invites_resource_name,
+                );
+            })?;
+        let entries_resource_name = resource_name!("/groups/{id}/entries");
+        let entries_id_url = request
+            .url_for(entries_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    entries_resource_name,
+                );
+            })?;
+        let lists_resource_name = resource_name!("/groups/{id}/lists");
+        let lists_id_url = request
+            .url_for(lists_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    lists_resource_name,
+                );
+            })?;
+        let activity_resource_name = resource_name!("/groups/{id}/activity");
+        let activity_id_url = request
+            .url_for(activity_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    activity_resource_name,
+                );
+            })?;
+        let archives_resource_name = resource_name!("/groups/{id}/archives");
+        let archives_id_url = request
+            .url_for(archives_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    archives_resource_name,
+                );
+            })?;
+        let stores_resource_name = resource_name!("/groups/{id}/stores");
+        let stores_id_url = request
+            .url_for(stores_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    stores_resource_name,
+                );
+            })?;
+        let mut sub_resources = vec![users_id_url.to_string()];
+        if role.can_manage_group() {
+            sub_resources.push(invites_id_url.to_string());
+        }
+        sub_resources.extend([
+            entries_id_url.to_string(),
+            lists_id_url.to_string(),
+            activity_id_url.to_string(),
+            archives_id_url.to_string(),
+            stores_id_url.to_string(),
+        ]);
 
         Ok(RestResource {
             resource: self,
             links: vec![self_id_url.to_string()],
-            sub_resources,
+            sub_resources: Some(sub_resources),
         })
     }
 }
@@ -114,19 +283,262 @@ pub(super) struct Entry {
     pub note: Option<String>,
     pub created: DateTime<Utc>,
     pub bought: Option<DateTime<Utc>>,
+    // the user that bought the entry, null if it hasn't been bought
+    // or the user has been deleted since
+    pub bought_by: Option<i64>,
+    // deleted entries are in the trash until they are restored or purged
+    pub deleted: Option<DateTime<Utc>>,
+    pub deleted_by: Option<i64>,
+    // archived entries are hidden from the lists, but can still be found in their archive
+    pub archive_id: Option<i64>,
+    // the trip during which the entry has been bought and the price that was paid for it
+    pub trip_id: Option<i64>,
+    pub price: Option<Decimal>,
+    // an iso 4217 code, if it is known
+    pub currency: Option<String>,
+    // the store where the entry has been bought
+    pub store_id: Option<i64>,
+    // increases with every change, it is also the etag of the entry
+    pub version: i64,
     pub user_id: i64,
+    pub list_id: i64,
+    // the group of the list, null for personal lists
     pub group_id: Option<i64>,
 }
 
 impl Entry {
+    pub fn version_etag(version: i64) -> EntityTag {
+        EntityTag::new_strong(version.to_string())
+    }
+
+    pub fn etag(&self) -> EntityTag {
+        Entry::version_etag(self.version)
+    }
+
     pub fn rest_resource(
         &self,
         request: &actix_web::HttpRequest,
-    ) -> Result<RestResource<Entry>, UrlGenerationError> {
+    ) -> Result<RestResource<'_, Entry>, UrlGenerationError> {
         let id_string_array = [self.id.to_string()];
         let self_resource_name = resource_name!("/entries/{id}");
         let self_id_url = request
-            .url_for(self_resource_name, id_string_array)
+            .url_for(self_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        let history_resource_name = resource_name!("/entries/{id}/history");
+        let history_id_url = request
+            .url_for(history_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    history_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_id_url.to_string()],
+            sub_resources: Some(vec![history_id_url.to_string()]),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "entry_event_kind", rename_all = "lowercase")]
+pub(super) enum EntryEventKind {
+    Created,
+    Updated,
+    Bought,
+    Unbought,
+    Deleted,
+    Restored,
+    Archived,
+    // the entry has been deleted permanently, this is only published live and not part of the history
+    Purged,
+}
+
+// ordered from the least to the most active status, like the type of the database
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "presence_status", rename_all = "lowercase")]
+pub(super) enum PresenceStatus {
+    Viewing,
+    Shopping,
+}
+
+// a user that is connected to the channel of a list
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Presence {
+    pub user_id: i64,
+    pub display_name: String,
+    pub status: PresenceStatus,
+}
+
+// a change of an entry, as shown in its history and the activity of its group
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct EntryEvent {
+    pub id: i64,
+    pub entry_id: i64,
+    pub list_id: i64,
+    pub group_id: Option<i64>,
+    // null if the user has been deleted since
+    pub actor_id: Option<i64>,
+    pub kind: EntryEventKind,
+    // maps the name of each changed field to its old and new value
+    pub changes: serde_json::Value,
+    pub created: DateTime<Utc>,
+}
+
+impl EntryEvent {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, EntryEvent>, UrlGenerationError> {
+        // events don't have their own resource, they link to the history they are part of
+        let history_resource_name = resource_name!("/entries/{id}/history");
+        let history_url = request
+            .url_for(history_resource_name, [self.entry_id.to_string()])
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    history_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![history_url.to_string()],
+            sub_resources: None,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct List {
+    pub id: i64,
+    pub name: String,
+    // exactly one of group_id and user_id is set
+    pub group_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub is_default: bool,
+    pub created: DateTime<Utc>,
+}
+
+impl List {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, List>, UrlGenerationError> {
+        let id_string_array = [self.id.to_string()];
+        let self_resource_name = resource_name!("/lists/{id}");
+        let self_id_url = request
+            .url_for(self_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        let entries_resource_name = resource_name!("/lists/{id}/entries");
+        let entries_id_url = request
+            .url_for(entries_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    entries_resource_name,
+                );
+            })?;
+
+        let trips_resource_name = resource_name!("/lists/{id}/trips");
+        let trips_id_url = request
+            .url_for(trips_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    trips_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_id_url.to_string()],
+            sub_resources: Some(vec![entries_id_url.to_string(), trips_id_url.to_string()]),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Archive {
+    pub id: i64,
+    // exactly one of group_id and user_id is set
+    pub group_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub created_by: Option<i64>,
+    pub created: DateTime<Utc>,
+    pub entry_count: i64,
+}
+
+impl Archive {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, Archive>, UrlGenerationError> {
+        let id_string_array = [self.id.to_string()];
+        let self_resource_name = resource_name!("/archives/{id}");
+        let self_id_url = request
+            .url_for(self_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        let entries_resource_name = resource_name!("/archives/{id}/entries");
+        let entries_id_url = request
+            .url_for(entries_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    entries_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_id_url.to_string()],
+            sub_resources: Some(vec![entries_id_url.to_string()]),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Store {
+    pub id: i64,
+    pub name: String,
+    // exactly one of group_id and user_id is set
+    pub group_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub created: DateTime<Utc>,
+}
+
+impl Store {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, Store>, UrlGenerationError> {
+        let self_resource_name = resource_name!("/stores/{id}");
+        let self_id_url = request
+            .url_for(self_resource_name, [self.id.to_string()])
             .inspect_err(|_| {
                 log::error!(
                     "Failed to get url for resource name: {}",
@@ -141,3 +553,396 @@ impl Entry {
         })
     }
 }
+
+// a price that has been paid for a product, as part of its price history
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct ProductPrice {
+    pub entry_id: i64,
+    pub price: Decimal,
+    pub currency: Option<String>,
+    pub amount: f32,
+    pub unit: String,
+    pub store_id: Option<i64>,
+    pub store: Option<String>,
+    pub bought: DateTime<Utc>,
+    pub bought_by: Option<i64>,
+    pub list_id: i64,
+    pub group_id: Option<i64>,
+}
+
+// prices are only comparable if they are in the same currency and for the same unit
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct ProductPriceAverage {
+    pub currency: Option<String>,
+    pub unit: String,
+    // null for the average over all stores
+    pub store_id: Option<i64>,
+    pub store: Option<String>,
+    pub average_price: Decimal,
+    // null if all amounts are zero
+    pub average_unit_price: Option<Decimal>,
+    pub count: i64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct ProductPrices {
+    pub product: String,
+    pub averages: Vec<ProductPriceAverage>,
+    // the newest price comes first
+    pub history: Vec<ProductPrice>,
+}
+
+impl ProductPrices {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, ProductPrices>, UrlGenerationError> {
+        let self_resource_name = resource_name!("/products/{name}/prices");
+        let self_url = request
+            .url_for(self_resource_name, [&self.product])
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_url.to_string()],
+            sub_resources: None,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Trip {
+    pub id: i64,
+    pub list_id: i64,
+    pub group_id: Option<i64>,
+    // null if the user has been deleted since
+    pub shopper_id: Option<i64>,
+    pub store_id: Option<i64>,
+    pub started: DateTime<Utc>,
+    // null while the trip is still going on
+    pub ended: Option<DateTime<Utc>>,
+    pub duration_seconds: Option<i64>,
+    // the sums of the prices of the entries bought during the trip, one per currency
+    pub totals: Json<Vec<TripTotal>>,
+    pub entry_count: i64,
+}
+
+// prices in different currencies can't be added up
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(super) struct TripTotal {
+    pub currency: Option<String>,
+    pub total: Decimal,
+}
+
+impl Trip {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, Trip>, UrlGenerationError> {
+        let id_string_array = [self.id.to_string()];
+        let self_resource_name = resource_name!("/trips/{id}");
+        let self_id_url = request
+            .url_for(self_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        let entries_resource_name = resource_name!("/trips/{id}/entries");
+        let entries_id_url = request
+            .url_for(entries_resource_name, &id_string_array)
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    entries_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_id_url.to_string()],
+            sub_resources: Some(vec![entries_id_url.to_string()]),
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Invite {
+    pub id: i64,
+    pub group_id: i64,
+    pub single_use: bool,
+    pub uses: i32,
+    pub expires: Option<DateTime<Utc>>,
+    pub created_by: i64,
+    pub created: DateTime<Utc>,
+}
+
+impl Invite {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, Invite>, UrlGenerationError> {
+        let self_resource_name = resource_name!("/groups/{id}/invites/{invite_id}");
+        let self_id_url = request
+            .url_for(
+                self_resource_name,
+                [self.group_id.to_string(), self.id.to_string()],
+            )
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_id_url.to_string()],
+            sub_resources: None,
+        })
+    }
+}
+
+// only the response to creating an invite contains the token,
+// afterwards there is no way to get it again
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct NewInvite {
+    #[serde(flatten)]
+    pub invite: Invite,
+    pub token: String,
+}
+
+impl NewInvite {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, NewInvite>, UrlGenerationError> {
+        let invite_rest_resource = self.invite.rest_resource(request)?;
+
+        // this is the url that has to be shared with the invited user
+        let accept_resource_name = resource_name!("/invites/{token}");
+        let accept_url = request
+            .url_for(accept_resource_name, [&self.token])
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    accept_resource_name,
+                );
+            })?;
+        let mut links = invite_rest_resource.links;
+        links.push(accept_url.to_string());
+
+        Ok(RestResource {
+            resource: self,
+            links,
+            sub_resources: invite_rest_resource.sub_resources,
+        })
+    }
+}
+
+// everything that is stored about a user, as returned by the export
+// it is meant to be downloaded, so it does not contain any links
+#[derive(Serialize)]
+pub(super) struct UserExport {
+    pub user: ExportedUser,
+    pub memberships: Vec<ExportedMembership>,
+    // all entries the user created, including the ones in groups the user is not a member of anymore
+    pub entries: Vec<Entry>,
+    // only the personal lists, group lists belong to the group
+    pub lists: Vec<List>,
+    // only the personal stores, like the lists
+    pub stores: Vec<Store>,
+    // all changes of entries the user made
+    pub entry_events: Vec<EntryEvent>,
+    pub invites: Vec<Invite>,
+    pub exported: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ExportedUser {
+    pub id: i64,
+    pub username: String,
+    pub display_name: String,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub(super) struct ExportedMembership {
+    pub group_id: i64,
+    pub group_name: String,
+    pub role: GroupRole,
+    pub joined: DateTime<Utc>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct Session {
+    pub id: i64,
+    pub created: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, Session>, UrlGenerationError> {
+        let self_resource_name = resource_name!("/sessions/{id}");
+        let self_id_url = request
+            .url_for(self_resource_name, [self.id.to_string()])
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_id_url.to_string()],
+            sub_resources: None,
+        })
+    }
+}
+
+// only the response to logging in contains the token,
+// afterwards there is no way to get it again
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct NewSession {
+    #[serde(flatten)]
+    pub session: Session,
+    pub token: String,
+}
+
+impl NewSession {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, NewSession>, UrlGenerationError> {
+        let session_rest_resource = self.session.rest_resource(request)?;
+
+        Ok(RestResource {
+            resource: self,
+            links: session_rest_resource.links,
+            sub_resources: session_rest_resource.sub_resources,
+        })
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct ApiKey {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub read_only: bool,
+    pub group_id: Option<i64>,
+    pub expires: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, ApiKey>, UrlGenerationError> {
+        let self_resource_name = resource_name!("/users/{identifier}/api-keys/{id}");
+        let self_id_url = request
+            .url_for(
+                self_resource_name,
+                [self.user_id.to_string(), self.id.to_string()],
+            )
+            .inspect_err(|_| {
+                log::error!(
+                    "Failed to get url for resource name: {}",
+                    self_resource_name,
+                );
+            })?;
+
+        Ok(RestResource {
+            resource: self,
+            links: vec![self_id_url.to_string()],
+            sub_resources: None,
+        })
+    }
+}
+
+// like with sessions, the key is only part of the response to creating it
+#[derive(Serialize, Clone, Debug)]
+pub(super) struct NewApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+impl NewApiKey {
+    pub fn rest_resource(
+        &self,
+        request: &actix_web::HttpRequest,
+    ) -> Result<RestResource<'_, NewApiKey>, UrlGenerationError> {
+        let api_key_rest_resource = self.api_key.rest_resource(request)?;
+
+        Ok(RestResource {
+            resource: self,
+            links: api_key_rest_resource.links,
+            sub_resources: api_key_rest_resource.sub_resources,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owners_and_admins_can_manage_the_group() {
+        assert!(GroupRole::Owner.can_manage_group());
+        assert!(GroupRole::Admin.can_manage_group());
+        assert!(!GroupRole::Member.can_manage_group());
+    }
+
+    #[test]
+    fn only_owners_can_change_roles() {
+        assert!(GroupRole::Owner.can_change_roles());
+        assert!(!GroupRole::Admin.can_change_roles());
+        assert!(!GroupRole::Member.can_change_roles());
+    }
+
+    #[test]
+    fn owners_can_remove_everyone() {
+        assert!(GroupRole::Owner.can_remove(GroupRole::Owner));
+        assert!(GroupRole::Owner.can_remove(GroupRole::Admin));
+        assert!(GroupRole::Owner.can_remove(GroupRole::Member));
+    }
+
+    #[test]
+    fn admins_can_only_remove_members() {
+        assert!(!GroupRole::Admin.can_remove(GroupRole::Owner));
+        assert!(!GroupRole::Admin.can_remove(GroupRole::Admin));
+        assert!(GroupRole::Admin.can_remove(GroupRole::Member));
+    }
+
+    #[test]
+    fn members_cant_remove_anyone() {
+        assert!(!GroupRole::Member.can_remove(GroupRole::Owner));
+        assert!(!GroupRole::Member.can_remove(GroupRole::Admin));
+        assert!(!GroupRole::Member.can_remove(GroupRole::Member));
+    }
+
+    #[test]
+    fn the_version_of_an_entry_is_its_strong_etag() {
+        let etag = Entry::version_etag(3);
+        assert!(!etag.weak);
+        assert_eq!(etag.tag(), "3");
+        assert!(etag.strong_eq(&Entry::version_etag(3)));
+        assert!(!etag.strong_eq(&Entry::version_etag(4)));
+    }
+}
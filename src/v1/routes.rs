@@ -5,6 +5,16 @@ use actix_web::{
 
 use super::handlers::*;
 
+// these routes can be used without being authenticated,
+// so they have to be registered outside of the scope that requires authentication
+pub fn configure_public_routes(config: &mut ServiceConfig) {
+    let users_resource = web::resource("/users")
+        .name(resource_name!("/users"))
+        .post(post_user)
+        .route(generate_options_route!("POST, OPTIONS"));
+    config.service(users_resource);
+}
+
 pub fn configure_routes(config: &mut ServiceConfig) {
     let index_resource = web::resource("")
         .name(resource_name!(""))
@@ -18,9 +28,72 @@ pub fn configure_routes(config: &mut ServiceConfig) {
         .get(get_user_by_id_or_username)
         // actix-web makes head response' body automatically empty, so get can be used as its handler
         .head(get_user_by_id_or_username)
-        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+        .patch(patch_user)
+        .delete(delete_user)
+        .route(generate_options_route!("GET, HEAD, PATCH, DELETE, OPTIONS"));
     config.service(user_by_identifier_resource);
 
+    let user_password_resource = web::resource("/users/{identifier}/password")
+        .name(resource_name!("/users/{identifier}/password"))
+        .put(put_user_password)
+        .route(generate_options_route!("PUT, OPTIONS"));
+    config.service(user_password_resource);
+
+    let user_export_resource = web::resource("/users/{identifier}/export")
+        .name(resource_name!("/users/{identifier}/export"))
+        .get(get_user_export)
+        .head(get_user_export)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(user_export_resource);
+
+    let user_api_keys_resource = web::resource("/users/{identifier}/api-keys")
+        .name(resource_name!("/users/{identifier}/api-keys"))
+        .get(get_api_keys)
+        .head(get_api_keys)
+        .post(post_api_key)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(user_api_keys_resource);
+
+    let user_api_key_by_id_resource = web::resource("/users/{identifier}/api-keys/{id}")
+        .name(resource_name!("/users/{identifier}/api-keys/{id}"))
+        .get(get_api_key_by_id)
+        .head(get_api_key_by_id)
+        .delete(delete_api_key)
+        .route(generate_options_route!("GET, HEAD, DELETE, OPTIONS"));
+    config.service(user_api_key_by_id_resource);
+
+    let user_entries_resource = web::resource("/users/{identifier}/entries")
+        .name(resource_name!("/users/{identifier}/entries"))
+        .get(get_user_entries)
+        .head(get_user_entries)
+        .post(post_user_entry)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(user_entries_resource);
+
+    let user_lists_resource = web::resource("/users/{identifier}/lists")
+        .name(resource_name!("/users/{identifier}/lists"))
+        .get(get_user_lists)
+        .head(get_user_lists)
+        .post(post_user_list)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(user_lists_resource);
+
+    let user_archives_resource = web::resource("/users/{identifier}/archives")
+        .name(resource_name!("/users/{identifier}/archives"))
+        .get(get_user_archives)
+        .head(get_user_archives)
+        .post(post_user_archive)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(user_archives_resource);
+
+    let user_stores_resource = web::resource("/users/{identifier}/stores")
+        .name(resource_name!("/users/{identifier}/stores"))
+        .get(get_user_stores)
+        .head(get_user_stores)
+        .post(post_user_store)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(user_stores_resource);
+
     let user_groups_resource = web::resource("/users/{identifier}/groups")
         .name(resource_name!("/users/{identifier}/groups"))
         .get(get_user_groups_by_id_or_username)
@@ -28,27 +101,212 @@ pub fn configure_routes(config: &mut ServiceConfig) {
         .route(generate_options_route!("GET, HEAD, OPTIONS"));
     config.service(user_groups_resource);
 
+    let sessions_resource = web::resource("/sessions")
+        .name(resource_name!("/sessions"))
+        .get(get_sessions)
+        .head(get_sessions)
+        .post(post_session)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(sessions_resource);
+
+    // has to be registered before "/sessions/{id}", which would match it otherwise
+    let current_session_resource = web::resource("/sessions/current")
+        .name(resource_name!("/sessions/current"))
+        .delete(delete_current_session)
+        .route(generate_options_route!("DELETE, OPTIONS"));
+    config.service(current_session_resource);
+
+    let session_by_id_resource = web::resource("/sessions/{id}")
+        .name(resource_name!("/sessions/{id}"))
+        .get(get_session_by_id)
+        .head(get_session_by_id)
+        .delete(delete_session)
+        .route(generate_options_route!("GET, HEAD, DELETE, OPTIONS"));
+    config.service(session_by_id_resource);
+
     let groups_resource = web::resource("/groups")
         .name(resource_name!("/groups"))
         .get(get_groups)
         .head(get_groups)
-        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+        .post(post_group)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
     config.service(groups_resource);
 
     let group_by_id_resource = web::resource("/groups/{id}")
         .name(resource_name!("/groups/{id}"))
         .get(get_group_by_id)
         .head(get_group_by_id)
-        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+        .patch(patch_group)
+        .delete(delete_group)
+        .route(generate_options_route!("GET, HEAD, PATCH, DELETE, OPTIONS"));
     config.service(group_by_id_resource);
 
     let group_users_resource = web::resource("/groups/{id}/users")
         .name(resource_name!("/groups/{id}/users"))
         .get(get_group_users)
         .head(get_group_users)
-        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+        .post(post_group_user)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
     config.service(group_users_resource);
 
+    let group_user_by_identifier_resource = web::resource("/groups/{id}/users/{identifier}")
+        .name(resource_name!("/groups/{id}/users/{identifier}"))
+        .get(get_group_user)
+        .head(get_group_user)
+        .patch(patch_group_user)
+        .delete(delete_group_user)
+        .route(generate_options_route!("GET, HEAD, PATCH, DELETE, OPTIONS"));
+    config.service(group_user_by_identifier_resource);
+
+    let group_entries_resource = web::resource("/groups/{id}/entries")
+        .name(resource_name!("/groups/{id}/entries"))
+        .get(get_group_entries)
+        .head(get_group_entries)
+        .post(post_group_entry)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(group_entries_resource);
+
+    let group_lists_resource = web::resource("/groups/{id}/lists")
+        .name(resource_name!("/groups/{id}/lists"))
+        .get(get_group_lists)
+        .head(get_group_lists)
+        .post(post_group_list)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(group_lists_resource);
+
+    let group_activity_resource = web::resource("/groups/{id}/activity")
+        .name(resource_name!("/groups/{id}/activity"))
+        .get(get_group_activity)
+        .head(get_group_activity)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(group_activity_resource);
+
+    let group_archives_resource = web::resource("/groups/{id}/archives")
+        .name(resource_name!("/groups/{id}/archives"))
+        .get(get_group_archives)
+        .head(get_group_archives)
+        .post(post_group_archive)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(group_archives_resource);
+
+    let group_entry_events_resource = web::resource("/groups/{id}/entries/events")
+        .name(resource_name!("/groups/{id}/entries/events"))
+        .get(get_group_entry_events)
+        .route(generate_options_route!("GET, OPTIONS"));
+    config.service(group_entry_events_resource);
+
+    let group_stores_resource = web::resource("/groups/{id}/stores")
+        .name(resource_name!("/groups/{id}/stores"))
+        .get(get_group_stores)
+        .head(get_group_stores)
+        .post(post_group_store)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(group_stores_resource);
+
+    let group_invites_resource = web::resource("/groups/{id}/invites")
+        .name(resource_name!("/groups/{id}/invites"))
+        .get(get_group_invites)
+        .head(get_group_invites)
+        .post(post_group_invite)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(group_invites_resource);
+
+    let group_invite_by_id_resource = web::resource("/groups/{id}/invites/{invite_id}")
+        .name(resource_name!("/groups/{id}/invites/{invite_id}"))
+        .get(get_group_invite)
+        .head(get_group_invite)
+        .delete(delete_group_invite)
+        .route(generate_options_route!("GET, HEAD, DELETE, OPTIONS"));
+    config.service(group_invite_by_id_resource);
+
+    let invite_by_token_resource = web::resource("/invites/{token}")
+        .name(resource_name!("/invites/{token}"))
+        .post(accept_invite)
+        .route(generate_options_route!("POST, OPTIONS"));
+    config.service(invite_by_token_resource);
+
+    let lists_by_id_resource = web::resource("/lists/{id}")
+        .name(resource_name!("/lists/{id}"))
+        .get(get_list_by_id)
+        .head(get_list_by_id)
+        .patch(patch_list)
+        .delete(delete_list)
+        .route(generate_options_route!("GET, HEAD, PATCH, DELETE, OPTIONS"));
+    config.service(lists_by_id_resource);
+
+    let list_entries_resource = web::resource("/lists/{id}/entries")
+        .name(resource_name!("/lists/{id}/entries"))
+        .get(get_list_entries)
+        .head(get_list_entries)
+        .post(post_list_entry)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(list_entries_resource);
+
+    let list_trips_resource = web::resource("/lists/{id}/trips")
+        .name(resource_name!("/lists/{id}/trips"))
+        .get(get_list_trips)
+        .head(get_list_trips)
+        .post(post_list_trip)
+        .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
+    config.service(list_trips_resource);
+
+    let list_channel_resource = web::resource("/lists/{id}/channel")
+        .name(resource_name!("/lists/{id}/channel"))
+        .get(get_list_channel)
+        .route(generate_options_route!("GET, OPTIONS"));
+    config.service(list_channel_resource);
+
+    let archive_by_id_resource = web::resource("/archives/{id}")
+        .name(resource_name!("/archives/{id}"))
+        .get(get_archive_by_id)
+        .head(get_archive_by_id)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(archive_by_id_resource);
+
+    let archive_entries_resource = web::resource("/archives/{id}/entries")
+        .name(resource_name!("/archives/{id}/entries"))
+        .get(get_archive_entries)
+        .head(get_archive_entries)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(archive_entries_resource);
+
+    let trip_by_id_resource = web::resource("/trips/{id}")
+        .name(resource_name!("/trips/{id}"))
+        .get(get_trip_by_id)
+        .head(get_trip_by_id)
+        .patch(patch_trip)
+        .route(generate_options_route!("GET, HEAD, PATCH, OPTIONS"));
+    config.service(trip_by_id_resource);
+
+    let trip_end_resource = web::resource("/trips/{id}/end")
+        .name(resource_name!("/trips/{id}/end"))
+        .post(end_trip)
+        .route(generate_options_route!("POST, OPTIONS"));
+    config.service(trip_end_resource);
+
+    let trip_entries_resource = web::resource("/trips/{id}/entries")
+        .name(resource_name!("/trips/{id}/entries"))
+        .get(get_trip_entries)
+        .head(get_trip_entries)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(trip_entries_resource);
+
+    let store_by_id_resource = web::resource("/stores/{id}")
+        .name(resource_name!("/stores/{id}"))
+        .get(get_store_by_id)
+        .head(get_store_by_id)
+        .patch(patch_store)
+        .delete(delete_store)
+        .route(generate_options_route!("GET, HEAD, PATCH, DELETE, OPTIONS"));
+    config.service(store_by_id_resource);
+
+    let product_prices_resource = web::resource("/products/{name}/prices")
+        .name(resource_name!("/products/{name}/prices"))
+        .get(get_product_prices)
+        .head(get_product_prices)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(product_prices_resource);
+
     let entries_resource = web::resource("/entries")
         .name(resource_name!("/entries"))
         .get(get_entries)
@@ -60,6 +318,26 @@ pub fn configure_routes(config: &mut ServiceConfig) {
         .route(generate_options_route!("GET, HEAD, POST, OPTIONS"));
     config.service(entries_resource);
 
+    // these have to be registered before "/entries/{id}", which would match them otherwise
+    let batch_resource = web::resource("/entries/batch")
+        .name(resource_name!("/entries/batch"))
+        .post(post_entries_batch)
+        .route(generate_options_route!("POST, OPTIONS"));
+    config.service(batch_resource);
+
+    let trash_resource = web::resource("/entries/trash")
+        .name(resource_name!("/entries/trash"))
+        .get(get_trash)
+        .head(get_trash)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(trash_resource);
+
+    let entry_events_resource = web::resource("/entries/events")
+        .name(resource_name!("/entries/events"))
+        .get(get_entry_events)
+        .route(generate_options_route!("GET, OPTIONS"));
+    config.service(entry_events_resource);
+
     let entries_by_id_resource = web::resource("/entries/{id}")
         .name(resource_name!("/entries/{id}"))
         .get(get_entry_by_id)
@@ -68,4 +346,17 @@ pub fn configure_routes(config: &mut ServiceConfig) {
         .delete(delete_entry)
         .route(generate_options_route!("GET, HEAD, PATCH, DELETE, OPTIONS"));
     config.service(entries_by_id_resource);
+
+    let entry_restore_resource = web::resource("/entries/{id}/restore")
+        .name(resource_name!("/entries/{id}/restore"))
+        .post(restore_entry)
+        .route(generate_options_route!("POST, OPTIONS"));
+    config.service(entry_restore_resource);
+
+    let entry_history_resource = web::resource("/entries/{id}/history")
+        .name(resource_name!("/entries/{id}/history"))
+        .get(get_entry_history)
+        .head(get_entry_history)
+        .route(generate_options_route!("GET, HEAD, OPTIONS"));
+    config.service(entry_history_resource);
 }
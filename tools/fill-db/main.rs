@@ -38,6 +38,10 @@ async fn main() {
             .unwrap();
         println!("Inserted users_groups_relations");
 
+        let insert_lists = include_str!(path_relative_to_crate_root!("db-filler-files/lists.sql"));
+        sqlx::query(insert_lists).execute(&pg_pool).await.unwrap();
+        println!("Inserted lists");
+
         let insert_entries =
             include_str!(path_relative_to_crate_root!("db-filler-files/entries.sql"));
         sqlx::query(insert_entries).execute(&pg_pool).await.unwrap();